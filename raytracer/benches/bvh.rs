@@ -0,0 +1,75 @@
+// Compares ray queries against a flat HittableList and against a BVH of the
+// same objects. Run with `cargo bench --bench bvh`.
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use raytracer::bvh::BvhNode;
+use raytracer::color::Color;
+use raytracer::constants::INFINITY;
+use raytracer::hittable::{HitRecord, Hittable};
+use raytracer::hittable_list::HittableList;
+use raytracer::material::Lambertian;
+use raytracer::ray::Ray;
+use raytracer::sphere::Sphere;
+use raytracer::vec3::Point3;
+
+const RAYS: usize = 20_000;
+
+fn random_point(rng: &mut StdRng, half_width: f64) -> Point3 {
+    Point3::new(
+        rng.random_range(-half_width..half_width),
+        rng.random_range(-half_width..half_width),
+        rng.random_range(-half_width..half_width),
+    )
+}
+
+fn trace_all(world: &dyn Hittable, rays: &[Ray]) -> (usize, Duration) {
+    let start = Instant::now();
+    let mut hits = 0;
+    let mut rec = HitRecord::new();
+    for r in rays {
+        if world.hit(black_box(r), 0.001, INFINITY, &mut rec) {
+            hits += 1;
+        }
+    }
+    (hits, start.elapsed())
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+
+    let rays: Vec<Ray> = (0..RAYS)
+        .map(|_| Ray::new(random_point(&mut rng, 60.0), random_point(&mut rng, 1.0)))
+        .collect();
+
+    println!("{:>10} {:>14} {:>14} {:>9}", "objects", "list", "bvh", "speedup");
+    for &count in &[100, 1_000, 10_000, 50_000] {
+        let mut list = HittableList::new();
+        for _ in 0..count {
+            list.add(Arc::new(Sphere::new(random_point(&mut rng, 50.0), 0.5, mat.clone())));
+        }
+
+        let build_start = Instant::now();
+        let bvh = BvhNode::new(list.objects().to_vec());
+        let build_time = build_start.elapsed();
+
+        let (list_hits, list_time) = trace_all(&list, &rays);
+        let (bvh_hits, bvh_time) = trace_all(&bvh, &rays);
+        assert_eq!(list_hits, bvh_hits, "BVH and list disagree");
+
+        println!(
+            "{:>10} {:>14?} {:>14?} {:>8.1}x   (build {:?})",
+            count,
+            list_time,
+            bvh_time,
+            list_time.as_secs_f64() / bvh_time.as_secs_f64(),
+            build_time
+        );
+    }
+}
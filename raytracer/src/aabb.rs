@@ -0,0 +1,80 @@
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+// Axis-aligned bounding box.
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    minimum: Point3,
+    maximum: Point3,
+}
+
+impl Aabb {
+    // Box spanning two opposite corners, given in any order.
+    pub fn new(a: Point3, b: Point3) -> Aabb {
+        Aabb {
+            minimum: Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            maximum: Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+    }
+
+    pub fn min(&self) -> Point3 {
+        self.minimum
+    }
+
+    pub fn max(&self) -> Point3 {
+        self.maximum
+    }
+
+    // Smallest box containing both boxes.
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Aabb {
+        Aabb::new(
+            Point3::new(
+                a.minimum.x().min(b.minimum.x()),
+                a.minimum.y().min(b.minimum.y()),
+                a.minimum.z().min(b.minimum.z()),
+            ),
+            Point3::new(
+                a.maximum.x().max(b.maximum.x()),
+                a.maximum.y().max(b.maximum.y()),
+                a.maximum.z().max(b.maximum.z()),
+            ),
+        )
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.minimum + self.maximum)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d: Vec3 = self.maximum - self.minimum;
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    // For each axis, the ray parameters where it enters and exits the slab between
+    // the two planes of the box on that axis.
+    pub fn slabs(&self, r: &Ray) -> [(f64, f64); 3] {
+        let mut slabs = [(0.0, 0.0); 3];
+        for (axis, slab) in slabs.iter_mut().enumerate() {
+            let inv_d = 1.0 / r.direction()[axis];
+            let t0 = (self.minimum[axis] - r.origin()[axis]) * inv_d;
+            let t1 = (self.maximum[axis] - r.origin()[axis]) * inv_d;
+            *slab = (t0.min(t1), t0.max(t1));
+        }
+        slabs
+    }
+
+    // The ray is inside the box where it is inside all three slabs at once,
+    // so it enters at the last slab entry and exits at the first slab exit.
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        let mut t_entry = t_min;
+        let mut t_exit = t_max;
+        for (t_near, t_far) in self.slabs(r) {
+            t_entry = t_entry.max(t_near);
+            t_exit = t_exit.min(t_far);
+            if t_exit < t_entry {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,154 @@
+// Thin, opaque disk of hot gas orbiting a gravity source, glowing like a
+// blackbody.
+//
+// The gas follows a thin disk temperature profile, hottest a little outside the
+// inner edge. Light is shifted by the Doppler effect of the orbital motion and by
+// climbing out of the gravity well, both for the direction the ray actually has
+// where it reaches the disk (after bending). The observer is taken to be far from
+// the mass. A blackbody seen with a frequency shift g looks like a blackbody at g
+// times the temperature, which also brightens the approaching side (beaming).
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::blackbody::blackbody;
+use crate::color::{self, Color};
+use crate::gravity::GravitySource;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::{Material, ScatterRecord};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{self, Point3, Vec3};
+
+pub struct AccretionDisk {
+    center: Point3,
+    normal: Vec3,
+    // Direction in the disk plane where the u texture coordinate starts.
+    tangent: Vec3,
+    inner_radius: f64,
+    outer_radius: f64,
+    emission: Arc<DiskEmission>,
+}
+
+struct DiskEmission {
+    center: Point3,
+    normal: Vec3,
+    inner_radius: f64,
+    schwarzschild_radius: f64,
+    peak_temperature: f64,
+    // Maps blackbody radiance to scene units.
+    scale: f64,
+}
+
+impl AccretionDisk {
+    // Disk around `source` in the plane through it perpendicular to `normal`. The
+    // gas orbits counterclockwise seen from the side `normal` points to.
+    // `brightness` is the luminance of gas at `peak_temperature`, without shifts.
+    pub fn new(
+        source: &GravitySource,
+        normal: Vec3,
+        inner_radius: f64,
+        outer_radius: f64,
+        peak_temperature: f64,
+        brightness: f64,
+    ) -> AccretionDisk {
+        let normal = vec3::unit_vector(normal);
+        let emission = DiskEmission {
+            center: source.position,
+            normal,
+            inner_radius,
+            schwarzschild_radius: source.schwarzschild_radius(),
+            peak_temperature,
+            scale: brightness / color::luminance(blackbody(peak_temperature)),
+        };
+        // Any direction in the plane will do.
+        let helper = if normal.x().abs() < 0.9 {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        };
+        AccretionDisk {
+            center: source.position,
+            normal,
+            tangent: vec3::unit_vector(vec3::cross(normal, helper)),
+            inner_radius,
+            outer_radius,
+            emission: Arc::new(emission),
+        }
+    }
+}
+
+impl Hittable for AccretionDisk {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let denom = vec3::dot(r.direction(), self.normal);
+        if denom.abs() < 1e-12 {
+            return false;
+        }
+        let t = vec3::dot(self.center - r.origin(), self.normal) / denom;
+        if t <= t_min || t_max <= t {
+            return false;
+        }
+
+        let p = r.at(t);
+        let dist2 = (p - self.center).length_squared();
+        if dist2 < self.inner_radius * self.inner_radius || dist2 > self.outer_radius * self.outer_radius {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        rec.set_face_normal(r, self.normal);
+
+        // u goes once around the disk, in the direction the gas orbits, and v
+        // from the inner to the outer edge.
+        let d = p - self.center;
+        let angle = f64::atan2(
+            vec3::dot(d, vec3::cross(self.normal, self.tangent)),
+            vec3::dot(d, self.tangent),
+        );
+        rec.u = (angle / (2.0 * PI)).rem_euclid(1.0);
+        rec.v = (dist2.sqrt() - self.inner_radius) / (self.outer_radius - self.inner_radius);
+        rec.mat = Some(self.emission.clone());
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.outer_radius, self.outer_radius, self.outer_radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+}
+
+impl DiskEmission {
+    // Thin disk profile T ~ r^-3/4 (1 - sqrt(r_in / r))^1/4, which peaks at
+    // r = 49/36 r_in.
+    fn temperature(&self, r: f64) -> f64 {
+        let profile = |x: f64| x.powf(-0.75) * (1.0 - x.powf(-0.5)).max(0.0).powf(0.25);
+        self.peak_temperature * profile(r / self.inner_radius) / profile(49.0 / 36.0)
+    }
+}
+
+impl Material for DiskEmission {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord, _sampler: &mut dyn Sampler) -> bool {
+        false
+    }
+
+    fn emitted(&self, r_in: &Ray, rec: &HitRecord) -> Color {
+        let r_vec = rec.p - self.center;
+        let r = r_vec.length();
+        let r_s = self.schwarzschild_radius;
+
+        // Circular orbit speed, measured by an observer hovering at that radius.
+        let beta = f64::sqrt(r_s / (2.0 * (r - r_s))).min(0.999);
+        let gamma = 1.0 / f64::sqrt(1.0 - beta * beta);
+        let orbit_dir = vec3::unit_vector(vec3::cross(self.normal, r_vec));
+
+        // The light travels back along the traced ray.
+        let to_observer = -vec3::unit_vector(r_in.direction());
+        let doppler = 1.0 / (gamma * (1.0 - beta * vec3::dot(orbit_dir, to_observer)));
+        let gravitational = f64::sqrt((1.0 - r_s / r).max(0.0));
+
+        let shift = doppler * gravitational;
+        self.scale * blackbody(shift * self.temperature(r))
+    }
+}
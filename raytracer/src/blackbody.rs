@@ -0,0 +1,52 @@
+// Color of thermal light: the Planck spectrum for a temperature, weighted with
+// the CIE 1931 color matching functions and converted to linear sRGB.
+
+use crate::color::Color;
+
+const C1: f64 = 1.191_042_97e-16; // 2 h c^2, W m^2 / sr
+const C2: f64 = 1.438_776_9e-2; // h c / k, m K
+
+// Spectral radiance in W / (sr m^2 m) at `wavelength` meters.
+pub fn planck(wavelength: f64, temperature: f64) -> f64 {
+    C1 / wavelength.powi(5) / f64::exp_m1(C2 / (wavelength * temperature))
+}
+
+// Linear sRGB radiance of a blackbody, in W / (sr m^2) weighted by the matching
+// functions. Colors outside the sRGB gamut are clipped to it.
+pub fn blackbody(temperature: f64) -> Color {
+    if temperature <= 0.0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+
+    const STEP: f64 = 5.0; // nm
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+    let mut lambda = 380.0;
+    while lambda <= 780.0 {
+        let b = planck(lambda * 1e-9, temperature) * STEP * 1e-9;
+        let (cx, cy, cz) = cie_xyz(lambda);
+        x += b * cx;
+        y += b * cy;
+        z += b * cz;
+        lambda += STEP;
+    }
+
+    Color::new(
+        (3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z).max(0.0),
+        (-0.969_266_0 * x + 1.876_010_8 * y + 0.041_556_0 * z).max(0.0),
+        (0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z).max(0.0),
+    )
+}
+
+// Multi-lobe fit of the CIE 1931 2° observer (Wyman, Sloan and Shirley, 2013),
+// `lambda` in nanometers.
+fn cie_xyz(lambda: f64) -> (f64, f64, f64) {
+    let g = |mu: f64, sigma_low: f64, sigma_high: f64| {
+        let sigma = if lambda < mu { sigma_low } else { sigma_high };
+        let t = (lambda - mu) / sigma;
+        f64::exp(-0.5 * t * t)
+    };
+    let x = 1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2);
+    let y = 0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1);
+    let z = 1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8);
+    (x, y, z)
+}
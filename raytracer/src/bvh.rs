@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+
+// Number of buckets the centroids are sorted into when looking for a split.
+const SAH_BUCKETS: usize = 16;
+
+// Bounding-volume hierarchy: a binary tree of boxes, each enclosing its children,
+// so a ray can skip every object whose box it misses.
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    // Panics if `objects` is empty or contains an unbounded object.
+    pub fn new(objects: Vec<Arc<dyn Hittable>>) -> BvhNode {
+        assert!(!objects.is_empty(), "BvhNode::new: no objects");
+        let mut items: Vec<(Aabb, Arc<dyn Hittable>)> = objects
+            .into_iter()
+            .map(|object| {
+                let bbox = object
+                    .bounding_box()
+                    .expect("BvhNode::new: object without a bounding box");
+                (bbox, object)
+            })
+            .collect();
+        BvhNode::build(&mut items)
+    }
+
+    fn build(items: &mut [(Aabb, Arc<dyn Hittable>)]) -> BvhNode {
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match items.len() {
+            1 => (items[0].1.clone(), items[0].1.clone()),
+            2 => (items[0].1.clone(), items[1].1.clone()),
+            _ => {
+                let mid = sah_partition(items);
+                let (l, r) = items.split_at_mut(mid);
+                (BvhNode::child(l), BvhNode::child(r))
+            }
+        };
+
+        let bbox = Aabb::surrounding(
+            &left.bounding_box().unwrap(),
+            &right.bounding_box().unwrap(),
+        );
+        BvhNode { left, right, bbox }
+    }
+
+    fn child(items: &mut [(Aabb, Arc<dyn Hittable>)]) -> Arc<dyn Hittable> {
+        if items.len() == 1 {
+            return items[0].1.clone();
+        }
+        Arc::new(BvhNode::build(items))
+    }
+}
+
+// Reorder `items` and return the split index with the lowest surface area heuristic
+// cost: the chance of a ray hitting a child box (its area relative to the parent)
+// times the number of objects in it, summed over both children.
+fn sah_partition(items: &mut [(Aabb, Arc<dyn Hittable>)]) -> usize {
+    let centroids = items
+        .iter()
+        .map(|(b, _)| Aabb::new(b.centroid(), b.centroid()))
+        .reduce(|a, b| Aabb::surrounding(&a, &b))
+        .unwrap();
+    let extent = centroids.max() - centroids.min();
+
+    // Split along the axis where the centroids are spread out the most.
+    let axis = if extent.x() > extent.y() && extent.x() > extent.z() {
+        0
+    } else if extent.y() > extent.z() {
+        1
+    } else {
+        2
+    };
+
+    items.sort_by(|a, b| a.0.centroid()[axis].total_cmp(&b.0.centroid()[axis]));
+
+    // All centroids in the same spot, no split is better than another.
+    if extent[axis] <= 0.0 {
+        return items.len() / 2;
+    }
+
+    // Bucket the sorted items by centroid position, then try a split at every
+    // bucket boundary.
+    let bucket_of = |b: &Aabb| {
+        let offset = (b.centroid()[axis] - centroids.min()[axis]) / extent[axis];
+        ((offset * SAH_BUCKETS as f64) as usize).min(SAH_BUCKETS - 1)
+    };
+    let mut counts = [0usize; SAH_BUCKETS];
+    let mut bounds: [Option<Aabb>; SAH_BUCKETS] = [None; SAH_BUCKETS];
+    for (b, _) in items.iter() {
+        let i = bucket_of(b);
+        counts[i] += 1;
+        bounds[i] = Some(match bounds[i] {
+            Some(acc) => Aabb::surrounding(&acc, b),
+            None => *b,
+        });
+    }
+
+    let merge = |acc: Option<Aabb>, b: &Option<Aabb>| match (acc, b) {
+        (Some(a), Some(b)) => Some(Aabb::surrounding(&a, b)),
+        (a, b) => a.or(*b),
+    };
+
+    let mut best_cost = f64::INFINITY;
+    let mut best_split = items.len() / 2;
+    let mut left_count = 0;
+    let mut left_bounds = None;
+    for split in 1..SAH_BUCKETS {
+        left_count += counts[split - 1];
+        left_bounds = merge(left_bounds, &bounds[split - 1]);
+        let right_count = items.len() - left_count;
+        if left_count == 0 || right_count == 0 {
+            continue;
+        }
+        let right_bounds = bounds[split..].iter().fold(None, merge);
+
+        let cost = left_bounds.unwrap().surface_area() * left_count as f64
+            + right_bounds.unwrap().surface_area() * right_count as f64;
+        if cost < best_cost {
+            best_cost = cost;
+            best_split = left_count;
+        }
+    }
+    best_split
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        if !self.bbox.hit(r, t_min, t_max) {
+            return false;
+        }
+
+        let hit_left = self.left.hit(r, t_min, t_max, rec);
+        // Only accept right hits closer than the left one.
+        let hit_right = self
+            .right
+            .hit(r, t_min, if hit_left { rec.t } else { t_max }, rec);
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+}
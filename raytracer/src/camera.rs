@@ -1,38 +1,146 @@
+use crate::constants::{self, PI};
+use crate::onb;
 use crate::ray::Ray;
-use crate::vec3::{Point3, Vec3};
+use crate::sampler::Sampler;
+use crate::vec3::{self, Point3, Vec3};
+
+// Thin lens in front of the camera. Points at `focus_dist` along the view
+// direction are sharp, everything else is blurred by the aperture.
+#[derive(Copy, Clone, Debug)]
+pub struct Lens {
+    pub aperture_radius: f64,
+    pub focus_dist: f64,
+    // Number of aperture blades. 0 gives a round aperture, 3 or more a regular
+    // polygon, which shows up as the shape of out of focus highlights (bokeh).
+    pub blades: u32,
+    // Rotation of the polygonal aperture in degrees.
+    pub rotation: f64,
+}
+
+impl Lens {
+    // Infinitely small aperture, everything is in focus.
+    pub fn pinhole() -> Lens {
+        Lens {
+            aperture_radius: 0.0,
+            focus_dist: 1.0,
+            blades: 0,
+            rotation: 0.0,
+        }
+    }
+}
 
 pub struct Camera {
     origin: Point3,
     lower_left_corner: Point3,
     horizontal: Vec3,
     vertical: Vec3,
+    u: Vec3,
+    v: Vec3,
+    lens: Lens,
 }
 
 impl Camera {
+    // Camera at (0, 0, 2) looking down -z with a 16:9 image.
     pub fn new() -> Camera {
-        let aspect_ratio = 16.0 / 9.0;
-        let viewport_height = 2.0;
+        Camera::look_at(
+            Point3::new(0.0, 0.0, 2.0),
+            Point3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            16.0 / 9.0,
+        )
+    }
+
+    // Camera at `look_from` pointed at `look_at`, rolled so that `vup` points up
+    // in the image. `vfov` is the vertical field of view in degrees and
+    // `aspect_ratio` is image width over height.
+    pub fn look_at(
+        look_from: Point3,
+        look_at: Point3,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+    ) -> Camera {
+        Camera::with_lens(look_from, look_at, vup, vfov, aspect_ratio, Lens::pinhole())
+    }
+
+    // Same as `look_at`, but rays start from a random point on `lens`.
+    pub fn with_lens(
+        look_from: Point3,
+        look_at: Point3,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        lens: Lens,
+    ) -> Camera {
+        let theta = constants::degrees_to_radians(vfov);
+        let h = f64::tan(theta / 2.0);
+        let viewport_height = 2.0 * h;
         let viewport_width = aspect_ratio * viewport_height;
-        let focal_length = 1.0;
- 
-        let origin = Point3::new(0.0, 0.0, 2.0);
-        let horizontal = Vec3::new(viewport_width, 0.0, 0.0);
-        let vertical = Vec3::new(0.0, viewport_height, 0.0);
-        let lower_left_corner =
-            origin - horizontal / 2.0 - vertical / 2.0 - Vec3::new(0.0, 0.0, focal_length);
- 
+
+        // Orthonormal basis for the camera: w points backwards, u right and v up.
+        let w = vec3::unit_vector(look_from - look_at);
+        let u = vec3::unit_vector(vec3::cross(vup, w));
+        let v = vec3::cross(w, u);
+
+        // The viewport sits on the focus plane, so rays through the lens
+        // converge there.
+        let origin = look_from;
+        let horizontal = lens.focus_dist * viewport_width * u;
+        let vertical = lens.focus_dist * viewport_height * v;
+        let lower_left_corner = origin - horizontal / 2.0 - vertical / 2.0 - lens.focus_dist * w;
+
         Camera {
             origin,
             lower_left_corner,
             horizontal,
             vertical,
+            u,
+            v,
+            lens,
         }
     }
 
-    pub fn get_ray(&self, u: f64, v: f64) -> Ray {
-        Ray::new(
-            self.origin,
-            self.lower_left_corner + u * self.horizontal + v * self.vertical - self.origin,
-        )
+    // Ray through the point (s, t) of the viewport, where (0, 0) is the bottom left
+    // corner. The sampler picks where on the lens it starts.
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let target = self.lower_left_corner + s * self.horizontal + t * self.vertical;
+        if self.lens.aperture_radius <= 0.0 {
+            return Ray::new(self.origin, target - self.origin);
+        }
+
+        let rd = self.lens.aperture_radius * self.sample_aperture(sampler);
+        let offset = self.u * rd.x() + self.v * rd.y();
+        Ray::new(self.origin + offset, target - self.origin - offset)
+    }
+
+    // Uniform point on the unit aperture, in lens coordinates.
+    fn sample_aperture(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        if self.lens.blades < 3 {
+            let (u1, u2) = sampler.next_2d();
+            return onb::in_unit_disk(u1, u2);
+        }
+
+        // Split the polygon into identical triangles fanning out from the
+        // center, pick one, then pick a uniform point inside it.
+        let n = self.lens.blades;
+        let blade = ((sampler.next_1d() * n as f64) as u32).min(n - 1);
+        let rotation = constants::degrees_to_radians(self.lens.rotation);
+        let angle = |k: u32| rotation + 2.0 * PI * k as f64 / n as f64;
+        let a = Vec3::new(angle(blade).cos(), angle(blade).sin(), 0.0);
+        let b = Vec3::new(angle(blade + 1).cos(), angle(blade + 1).sin(), 0.0);
+
+        let (mut r1, mut r2) = sampler.next_2d();
+        if r1 + r2 > 1.0 {
+            r1 = 1.0 - r1;
+            r2 = 1.0 - r2;
+        }
+        r1 * a + r2 * b
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::new()
     }
 }
@@ -0,0 +1,242 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+
+use raytracer::exr::{ExrCompression, ExrPixelType};
+use raytracer::image_io::ImageFormat;
+use raytracer::sampler::SamplerKind;
+use raytracer::tonemap::{DisplayTransform, Dither, ToneMap};
+use raytracer::RenderSettings;
+
+const DEFAULT_ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "A simple ray tracer with gravitational lensing")]
+pub struct Cli {
+    /// Image width in pixels
+    #[arg(long, default_value_t = 1920, value_parser = clap::value_parser!(i32).range(2..))]
+    pub width: i32,
+
+    /// Image height in pixels [default: width / (16 / 9)]
+    #[arg(long, value_parser = clap::value_parser!(i32).range(2..))]
+    pub height: Option<i32>,
+
+    /// Samples per pixel
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(i32).range(1..))]
+    pub spp: i32,
+
+    /// Maximum number of bounces per path
+    #[arg(long, default_value_t = 15, value_parser = clap::value_parser!(i32).range(1..))]
+    pub max_depth: i32,
+
+    /// Longest integration step for rays bent by gravity
+    #[arg(long, default_value_t = 1.0, value_parser = positive_f64, allow_negative_numbers = true)]
+    pub gravity_step: f64,
+
+    /// Largest error per integration step for rays bent by gravity, in scene units
+    #[arg(long, default_value_t = 1e-4, value_parser = positive_f64, allow_negative_numbers = true)]
+    pub gravity_tolerance: f64,
+
+    /// Total gravity simulation time per ray before it escapes to the sky
+    #[arg(long, default_value_t = 10.0, value_parser = positive_f64, allow_negative_numbers = true)]
+    pub gravity_horizon: f64,
+
+    /// Output file, `-` writes to stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: PathBuf,
+
+    /// Output image format [default: from the output file extension, else ppm]
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Sample type for OpenEXR output
+    #[arg(long, value_enum, default_value_t = ExrPixel::Half)]
+    pub exr_pixel: ExrPixel,
+
+    /// Compression for OpenEXR output
+    #[arg(long, value_enum, default_value_t = ExrZip::Zip)]
+    pub exr_compression: ExrZip,
+
+    /// Exposure adjustment in stops for PPM and PNG output
+    #[arg(long, default_value_t = 0.0, value_parser = finite_f64, allow_negative_numbers = true)]
+    pub exposure: f64,
+
+    /// Tone mapping operator for PPM and PNG output
+    #[arg(long, value_enum, default_value_t = ToneMapper::Clamp)]
+    pub tonemap: ToneMapper,
+
+    /// Dithering applied when quantizing PPM and PNG output
+    #[arg(long, value_enum, default_value_t = DitherMode::None)]
+    pub dither: DitherMode,
+
+    /// Where the random numbers for each sample come from
+    #[arg(long, value_enum, default_value_t = SamplerMode::Independent)]
+    pub sampler: SamplerMode,
+
+    /// Seed for the per-pixel random streams
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Worker threads, 0 uses one per core
+    #[arg(long, default_value_t = 0)]
+    pub threads: usize,
+
+    /// TOML scene description [default: the built-in demo scene]
+    #[arg(long)]
+    pub scene: Option<PathBuf>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// ASCII P3 PPM
+    Ppm,
+    /// Binary P6 PPM
+    P6,
+    /// 8-bit PNG
+    Png,
+    /// 16-bit PNG
+    Png16,
+    /// Radiance RGBE (linear HDR)
+    Hdr,
+    /// Portable FloatMap (linear HDR)
+    Pfm,
+    /// OpenEXR (linear HDR)
+    Exr,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExrPixel {
+    /// 16-bit half floats
+    Half,
+    /// 32-bit floats
+    Float,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExrZip {
+    /// Uncompressed
+    None,
+    /// Lossless ZIP, 16 scanlines per block
+    Zip,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ToneMapper {
+    /// Clip values above 1
+    Clamp,
+    /// Reinhard, x / (1 + x)
+    Reinhard,
+    /// Hable's Uncharted 2 filmic curve
+    Hable,
+    /// ACES filmic curve (Narkowicz fit)
+    Aces,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DitherMode {
+    /// Plain rounding
+    None,
+    /// 8x8 Bayer matrix
+    Ordered,
+    /// 64x64 blue noise mask
+    BlueNoise,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SamplerMode {
+    /// Independent random numbers
+    Independent,
+    /// Jittered strata, shuffled per dimension
+    Stratified,
+    /// Owen-scrambled Halton sequence
+    Halton,
+    /// Owen-scrambled Sobol points
+    Sobol,
+    /// Sobol points spread over neighbouring pixels along a shuffled Z curve
+    BlueNoise,
+}
+
+impl Cli {
+    pub fn image_format(&self) -> ImageFormat {
+        let format = match self.format {
+            Some(OutputFormat::Ppm) => ImageFormat::P3,
+            Some(OutputFormat::P6) => ImageFormat::P6,
+            Some(OutputFormat::Png) => ImageFormat::Png8,
+            Some(OutputFormat::Png16) => ImageFormat::Png16,
+            Some(OutputFormat::Hdr) => ImageFormat::Hdr,
+            Some(OutputFormat::Pfm) => ImageFormat::Pfm,
+            Some(OutputFormat::Exr) => ImageFormat::Exr(ExrPixelType::Half, ExrCompression::Zip),
+            None => ImageFormat::from_path(&self.output).unwrap_or(ImageFormat::P3),
+        };
+
+        match format {
+            ImageFormat::Exr(..) => ImageFormat::Exr(
+                match self.exr_pixel {
+                    ExrPixel::Half => ExrPixelType::Half,
+                    ExrPixel::Float => ExrPixelType::Float,
+                },
+                match self.exr_compression {
+                    ExrZip::None => ExrCompression::None,
+                    ExrZip::Zip => ExrCompression::Zip,
+                },
+            ),
+            format => format,
+        }
+    }
+
+    pub fn display_transform(&self) -> DisplayTransform {
+        DisplayTransform {
+            exposure: self.exposure,
+            tone_map: match self.tonemap {
+                ToneMapper::Clamp => ToneMap::Clamp,
+                ToneMapper::Reinhard => ToneMap::Reinhard,
+                ToneMapper::Hable => ToneMap::Hable,
+                ToneMapper::Aces => ToneMap::Aces,
+            },
+            dither: match self.dither {
+                DitherMode::None => Dither::None,
+                DitherMode::Ordered => Dither::Ordered,
+                DitherMode::BlueNoise => Dither::BlueNoise,
+            },
+        }
+    }
+
+    pub fn render_settings(&self) -> RenderSettings {
+        RenderSettings {
+            image_width: self.width,
+            image_height: self
+                .height
+                .unwrap_or(((self.width as f64 / DEFAULT_ASPECT_RATIO) as i32).max(2)),
+            samples_per_pixel: self.spp,
+            max_depth: self.max_depth,
+            max_step: self.gravity_step,
+            tolerance: self.gravity_tolerance,
+            max_time: self.gravity_horizon,
+            sampler: match self.sampler {
+                SamplerMode::Independent => SamplerKind::Independent,
+                SamplerMode::Stratified => SamplerKind::Stratified,
+                SamplerMode::Halton => SamplerKind::Halton,
+                SamplerMode::Sobol => SamplerKind::Sobol,
+                SamplerMode::BlueNoise => SamplerKind::BlueNoise,
+            },
+            seed: self.seed,
+            threads: self.threads,
+        }
+    }
+}
+
+fn positive_f64(s: &str) -> Result<f64, String> {
+    let x: f64 = s.parse().map_err(|_| format!("`{}` is not a number", s))?;
+    if !x.is_finite() || x <= 0.0 {
+        return Err(format!("must be a positive, finite number (got {})", s));
+    }
+    Ok(x)
+}
+
+fn finite_f64(s: &str) -> Result<f64, String> {
+    let x: f64 = s.parse().map_err(|_| format!("`{}` is not a number", s))?;
+    if !x.is_finite() {
+        return Err(format!("must be a finite number (got {})", s));
+    }
+    Ok(x)
+}
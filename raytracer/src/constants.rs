@@ -1,21 +1,22 @@
 // Constants
 pub use std::f64::consts::PI;
-pub use std::f64::INFINITY;
-use rand::Rng;
+pub const INFINITY: f64 = f64::INFINITY;
 
 // Utility functions
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
-pub fn random_double() -> f64 {
-    // Return a random real in [0.0, 1.0)
-    rand::thread_rng().gen()
+// Derive an independent seed for one stream (e.g. one pixel) from a base seed.
+pub fn mix_seed(seed: u64, stream: u64) -> u64 {
+    splitmix64(seed ^ splitmix64(stream))
 }
- 
-pub fn random_double_range(min: f64, max: f64) -> f64 {
-    // Return a random real in [min, max)
-    min + (max - min) * random_double()
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
 }
 
 pub fn clamp(x: f64, min: f64, max: f64) -> f64 {
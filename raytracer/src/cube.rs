@@ -1,18 +1,21 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
+use crate::aabb::Aabb;
+use crate::constants::INFINITY;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{self, Point3, Vec3};
 use crate::material::Material;
 
 pub struct Cube {
     a: Point3, // Min Corner
     b: Point3, // Max Corner
-    mat: Rc<dyn Material>
+    mat: Arc<dyn Material>
 }
 
 impl Cube {
-    pub fn new(min: Point3, max: Point3, m: Rc <dyn Material>) -> Cube {
+    pub fn new(min: Point3, max: Point3, m: Arc<dyn Material>) -> Cube {
         Cube {
             a: min,
             b: max,
@@ -21,66 +24,143 @@ impl Cube {
     }
 }
 
-impl Hittable for Cube {
-    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
-        // Compute t values for x range.
-        let t_x0 = (self.a.x() - r.origin().x()) / r.direction().x();
-        let t_x1 = (self.b.x() - r.origin().x()) / r.direction().x();
-        let t_xMin = t_x0.min(t_x1); // X range entry
-        let t_xMax = t_x0.max(t_x1); // X range exit
+impl Cube {
+    // Faces turned towards `origin`, as (axis, coordinate of the face plane), and
+    // their total area. None of them when `origin` is inside.
+    fn faces_towards(&self, origin: Point3) -> (Vec<(usize, f64)>, f64) {
+        let size = self.b - self.a;
+        let mut faces = Vec::new();
+        let mut area = 0.0;
+        for axis in 0..3 {
+            let plane = if origin[axis] < self.a[axis] {
+                self.a[axis]
+            } else if origin[axis] > self.b[axis] {
+                self.b[axis]
+            } else {
+                continue;
+            };
+            faces.push((axis, plane));
+            area += size[(axis + 1) % 3] * size[(axis + 2) % 3];
+        }
+        (faces, area)
+    }
+}
 
-        // Compute t valin this case, im assuming only positive directions, not rendering anything behind the camera.ues for y range.
-        let t_y0 = (self.a.y() - r.origin().y()) / r.direction().y();
-        let t_y1 = (self.b.y() - r.origin().y()) / r.direction().y();
-        let t_yMin = t_y0.min(t_y1); // Y range entry
-        let t_yMax = t_y0.max(t_y1); // Y range exit
+impl Hittable for Cube {
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.a, self.b))
+    }
 
-        // Compute t values for z range.
-        let t_z0 = (self.a.z() - r.origin().z()) / r.direction().z();
-        let t_z1 = (self.b.z() - r.origin().z()) / r.direction().z();
-        let t_zMin = t_z0.min(t_z1); // Z range entry
-        let t_zMax = t_z0.max(t_z1); // Z range exit
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        // Compute the t ranges where the ray is between the x, y and z planes of the box.
+        let [(t_x_min, t_x_max), (t_y_min, t_y_max), (t_z_min, t_z_max)] =
+            self.bounding_box().unwrap().slabs(r);
 
         // Calculate t entry & exit of each Ray 
         // Since we know the entry and exit of the x, y, z ranges,
         // the entry is the last range thew ray enters.
         // In other words, the max of the entries.
-        let t_entry = t_xMin.max(t_yMin).max(t_zMin);
-        let t_exit = t_xMax.min(t_yMax).min(t_zMax);
+        let t_entry = t_x_min.max(t_y_min).max(t_z_min);
+        let t_exit = t_x_max.min(t_y_max).min(t_z_max);
 
-        if t_entry <= t_exit && t_entry < t_max && t_exit > t_min {
-            rec.t = t_entry;
-            rec.p = r.at(t_entry);
+        if t_entry > t_exit {
+            return false;
+        }
 
-        // Determine which face was hit:
-        let epsilon = 1e-6;
-        if (t_entry - t_xMin).abs() < epsilon {
-            // Hit on an x-face.
-            if r.direction().x() > 0.0 {
-                rec.normal = Vec3::new(-1.0, 0.0, 0.0);
+        // A ray starting outside the box hits the face it enters through.
+        // A ray starting inside (the entry is behind t_min) hits the face it leaves through.
+        let entering = t_entry > t_min && t_entry < t_max;
+        let t = if entering {
+            t_entry
+        } else if t_exit > t_min && t_exit < t_max {
+            t_exit
+        } else {
+            return false;
+        };
+        rec.t = t;
+        rec.p = r.at(t);
+
+        // Determine which face was hit: t is exactly one of the slab values it was picked from.
+        // The outward normal of an entry face points against the ray, an exit face along it.
+        let (t_x, t_y) = if entering {
+            (t_x_min, t_y_min)
+        } else {
+            (t_x_max, t_y_max)
+        };
+        let outward = |d: f64| {
+            let along = if d > 0.0 { 1.0 } else { -1.0 };
+            if entering {
+                -along
             } else {
-                rec.normal = Vec3::new(1.0, 0.0, 0.0);
+                along
             }
-        } else if (t_entry - t_yMin).abs() < epsilon {
+        };
+        let outward_normal = if t == t_x {
+            // Hit on an x-face.
+            Vec3::new(outward(r.direction().x()), 0.0, 0.0)
+        } else if t == t_y {
             // Hit on a y-face.
-            if r.direction().y() > 0.0 {
-                rec.normal = Vec3::new(0.0, -1.0, 0.0);
-            } else {
-                rec.normal = Vec3::new(0.0, 1.0, 0.0);
-            }
-        } else if (t_entry - t_zMin).abs() < epsilon {
+            Vec3::new(0.0, outward(r.direction().y()), 0.0)
+        } else {
             // Hit on a z-face.
-            if r.direction().z() > 0.0 {
-                rec.normal = Vec3::new(0.0, 0.0, -1.0);
-            } else {
-                rec.normal = Vec3::new(0.0, 0.0, 1.0);
-            }
-        }
-        // Optionally, use rec.set_face_normal(r, rec.normal) if you want to
-        // ensure the normal always points outward relative to the ray.
+            Vec3::new(0.0, 0.0, outward(r.direction().z()))
+        };
+        rec.set_face_normal(r, outward_normal);
+
+        // Each face gets the whole [0, 1] square, upright and unmirrored when
+        // seen from outside (up is +y, or -z on the top and bottom faces).
+        let size = self.b - self.a;
+        let local = rec.p - self.a;
+        // A flat box has no extent along one axis, so that coordinate is 0.
+        let scaled = |axis: usize| if size[axis] > 0.0 { local[axis] / size[axis] } else { 0.0 };
+        let (x, y, z) = (scaled(0), scaled(1), scaled(2));
+        (rec.u, rec.v) = match (outward_normal.x(), outward_normal.y(), outward_normal.z()) {
+            (nx, _, _) if nx > 0.0 => (1.0 - z, y),
+            (nx, _, _) if nx < 0.0 => (z, y),
+            (_, ny, _) if ny > 0.0 => (x, 1.0 - z),
+            (_, ny, _) if ny < 0.0 => (x, z),
+            (_, _, nz) if nz > 0.0 => (x, y),
+            _ => (1.0 - x, y),
+        };
         rec.mat = Some(self.mat.clone());
-        return true;
+        true
+    }
+
+    // Uniform over the faces seen from `origin`. A ray from outside enters the
+    // box through exactly one of them.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let (faces, area) = self.faces_towards(origin);
+        if faces.is_empty() || area <= 0.0 {
+            return 0.0;
+        }
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction), 0.001, INFINITY, &mut rec) {
+            return 0.0;
+        }
+        let to_hit = rec.p - origin;
+        let cosine = vec3::dot(rec.normal, vec3::unit_vector(to_hit)).abs();
+        to_hit.length_squared() / (cosine * area)
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (faces, area) = self.faces_towards(origin);
+        let size = self.b - self.a;
+
+        // Pick a face by its area, then a point on it.
+        let mut pick = sampler.next_1d() * area;
+        let (s, t) = sampler.next_2d();
+        for &(axis, plane) in &faces {
+            let (i, j) = ((axis + 1) % 3, (axis + 2) % 3);
+            let face_area = size[i] * size[j];
+            if pick < face_area || (axis, plane) == *faces.last().unwrap() {
+                let mut p = [0.0; 3];
+                p[axis] = plane;
+                p[i] = self.a[i] + s * size[i];
+                p[j] = self.a[j] + t * size[j];
+                return Vec3::new(p[0], p[1], p[2]) - origin;
+            }
+            pick -= face_area;
         }
-        false
+        self.a + 0.5 * size - origin
     }
 }
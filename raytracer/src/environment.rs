@@ -0,0 +1,274 @@
+// What rays that leave the scene see: a gradient, or images of the sky all
+// around.
+
+use std::f64::consts::PI;
+
+use crate::color::{self, Color};
+use crate::image::{ImageBuffer, Wrap};
+use crate::sampler::Sampler;
+use crate::vec3::{self, Vec3};
+
+pub trait Environment: Send + Sync {
+    // Light arriving from `direction`, which need not be a unit vector.
+    fn color(&self, direction: Vec3) -> Color;
+
+    // Light sampling, for environments bright and uneven enough to be worth it:
+    // `random` picks a direction, more often where the sky is bright, and
+    // `pdf_value` is the density of that choice per unit solid angle.
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    fn pdf_value(&self, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    fn random(&self, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new(0.0, 1.0, 0.0)
+    }
+}
+
+// Vertical blend between two colors.
+#[derive(Copy, Clone, Debug)]
+pub struct Gradient {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl Gradient {
+    pub fn new(bottom: Color, top: Color) -> Gradient {
+        Gradient { bottom, top }
+    }
+}
+
+impl Default for Gradient {
+    fn default() -> Gradient {
+        Gradient {
+            bottom: Color::new(0.81, 0.93, 0.96),
+            top: Color::new(0.28, 0.35, 0.50),
+        }
+    }
+}
+
+impl Environment for Gradient {
+    fn color(&self, direction: Vec3) -> Color {
+        let unit_direction = vec3::unit_vector(direction);
+        let t = 0.5 * (unit_direction.y() + 1.0);
+        (1.0 - t) * self.bottom + t * self.top
+    }
+}
+
+// Latitude-longitude panorama: x covers the full circle around the vertical axis,
+// y goes from straight up to straight down. The image center is seen looking
+// along -z.
+pub struct EquirectangularMap {
+    image: ImageBuffer,
+    rotation: f64,
+    intensity: f64,
+    // Running sums of the pixel weights for light sampling: over the rows, and
+    // along each row (width + 1 entries per row, starting at 0).
+    row_cdf: Vec<f64>,
+    pixel_cdf: Vec<f64>,
+}
+
+impl EquirectangularMap {
+    // `rotation` turns the map around the vertical axis, counterclockwise seen from
+    // above, in degrees. `intensity` scales the image values.
+    pub fn new(image: ImageBuffer, rotation: f64, intensity: f64) -> EquirectangularMap {
+        // Pixels are picked by brightness times the solid angle they cover,
+        // which shrinks towards the poles.
+        let (w, h) = (image.width(), image.height());
+        let mut row_cdf = vec![0.0; h + 1];
+        let mut pixel_cdf = vec![0.0; h * (w + 1)];
+        for y in 0..h {
+            let sin_theta = f64::sin(PI * (y as f64 + 0.5) / h as f64);
+            let row = &mut pixel_cdf[y * (w + 1)..(y + 1) * (w + 1)];
+            for x in 0..w {
+                row[x + 1] = row[x] + color::luminance(image.get(x, y)).max(0.0) * sin_theta;
+            }
+            row_cdf[y + 1] = row_cdf[y] + row[w];
+        }
+
+        EquirectangularMap {
+            image,
+            rotation: rotation.to_radians(),
+            intensity,
+            row_cdf,
+            pixel_cdf,
+        }
+    }
+
+    // Image position of a direction, both in [0, 1].
+    fn uv(&self, direction: Vec3) -> (f64, f64) {
+        let d = vec3::unit_vector(direction);
+        let phi = f64::atan2(d.x(), -d.z()) + self.rotation;
+        let theta = d.y().clamp(-1.0, 1.0).acos();
+        ((0.5 + phi / (2.0 * PI)).rem_euclid(1.0), theta / PI)
+    }
+
+    // Solid angle of a pixel in row `y`.
+    fn pixel_solid_angle(&self, y: usize) -> f64 {
+        let h = self.image.height() as f64;
+        let (top, bottom) = (PI * y as f64 / h, PI * (y + 1) as f64 / h);
+        (2.0 * PI / self.image.width() as f64) * (top.cos() - bottom.cos())
+    }
+
+    fn row(&self, y: usize) -> &[f64] {
+        let w = self.image.width();
+        &self.pixel_cdf[y * (w + 1)..(y + 1) * (w + 1)]
+    }
+}
+
+impl Environment for EquirectangularMap {
+    fn color(&self, direction: Vec3) -> Color {
+        let (u, v) = self.uv(direction);
+        self.intensity * self.image.bilinear(u, v, Wrap::Repeat, Wrap::Clamp)
+    }
+
+    fn is_light(&self) -> bool {
+        self.row_cdf.last().is_some_and(|&total| total > 0.0)
+    }
+
+    fn pdf_value(&self, direction: Vec3) -> f64 {
+        let (w, h) = (self.image.width(), self.image.height());
+        let total = self.row_cdf[h];
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let (u, v) = self.uv(direction);
+        let x = ((u * w as f64) as usize).min(w - 1);
+        let y = ((v * h as f64) as usize).min(h - 1);
+
+        // Chance of the pixel, spread over the solid angle it covers.
+        let row = self.row(y);
+        let p = (row[x + 1] - row[x]) / total;
+        p / self.pixel_solid_angle(y)
+    }
+
+    fn random(&self, sampler: &mut dyn Sampler) -> Vec3 {
+        let (w, h) = (self.image.width(), self.image.height());
+        let pick = |cdf: &[f64], u: f64| {
+            let target = u * cdf[cdf.len() - 1];
+            // First bin whose end is past the target; empty bins are never picked.
+            (cdf[1..].partition_point(|&c| c <= target)).min(cdf.len() - 2)
+        };
+        let (row, column) = sampler.next_2d();
+        let y = pick(&self.row_cdf, row);
+        let x = pick(self.row(y), column);
+
+        // Uniform over the pixel's solid angle: uniform in phi and in cos(theta).
+        let (s, t) = sampler.next_2d();
+        let u = (x as f64 + s) / w as f64;
+        let (top, bottom) = (PI * y as f64 / h as f64, PI * (y + 1) as f64 / h as f64);
+        let cos_theta = top.cos() + t * (bottom.cos() - top.cos());
+        let sin_theta = f64::sqrt((1.0 - cos_theta * cos_theta).max(0.0));
+        let phi = (u - 0.5) * 2.0 * PI - self.rotation;
+        Vec3::new(sin_theta * phi.sin(), cos_theta, -sin_theta * phi.cos())
+    }
+}
+
+// Skybox made of six square images, in the order right (+x), left (-x), top (+y),
+// bottom (-y), front (-z) and back (+z). Each face is seen upright and unmirrored
+// from the inside. The top face has its bottom edge against the front face, the
+// bottom face its top edge, the same way they fold up from a cross layout.
+pub struct CubeMap {
+    faces: [ImageBuffer; 6],
+    rotation: f64,
+    intensity: f64,
+}
+
+impl CubeMap {
+    // `rotation` and `intensity` work as for `EquirectangularMap`.
+    pub fn new(faces: [ImageBuffer; 6], rotation: f64, intensity: f64) -> CubeMap {
+        CubeMap {
+            faces,
+            rotation: rotation.to_radians(),
+            intensity,
+        }
+    }
+
+    // Cut the faces out of a single image holding the unfolded cube, either a
+    // horizontal cross four faces wide,
+    //
+    //          top
+    //     left front right back
+    //          bottom
+    //
+    // or a vertical cross three faces wide with the back face upside down below
+    // the bottom one. Returns None for images of any other shape.
+    pub fn from_cross(image: &ImageBuffer, rotation: f64, intensity: f64) -> Option<CubeMap> {
+        let (w, h) = (image.width(), image.height());
+        // (column, row) of right, left, top, bottom, front and back.
+        let (size, cells, back_flipped) = if w * 3 == h * 4 && w % 4 == 0 {
+            (w / 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)], false)
+        } else if w * 4 == h * 3 && w % 3 == 0 {
+            (w / 3, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)], true)
+        } else {
+            return None;
+        };
+        if size == 0 {
+            return None;
+        }
+
+        let faces = cells.map(|(col, row)| {
+            let mut face = ImageBuffer::new(size, size);
+            for y in 0..size {
+                for x in 0..size {
+                    face.set(x, y, image.get(col * size + x, row * size + y));
+                }
+            }
+            face
+        });
+        let [right, left, top, bottom, front, back] = faces;
+        let back = if back_flipped { rotate_half_turn(&back) } else { back };
+        Some(CubeMap::new([right, left, top, bottom, front, back], rotation, intensity))
+    }
+}
+
+fn rotate_half_turn(image: &ImageBuffer) -> ImageBuffer {
+    let (w, h) = (image.width(), image.height());
+    let mut rotated = ImageBuffer::new(w, h);
+    for y in 0..h {
+        for x in 0..w {
+            rotated.set(w - 1 - x, h - 1 - y, image.get(x, y));
+        }
+    }
+    rotated
+}
+
+impl Environment for CubeMap {
+    fn color(&self, direction: Vec3) -> Color {
+        // Turn the direction the same way the equirectangular map turns.
+        let (sin, cos) = self.rotation.sin_cos();
+        let (x, y, z) = (direction.x(), direction.y(), direction.z());
+        let (x, z) = (x * cos - z * sin, z * cos + x * sin);
+
+        // Face of the largest component, and the position on it in [-1, 1],
+        // right and down.
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+        let (face, s, t) = if ax >= ay && ax >= az {
+            if x > 0.0 {
+                (0, z / ax, -y / ax)
+            } else {
+                (1, -z / ax, -y / ax)
+            }
+        } else if ay >= az {
+            if y > 0.0 {
+                (2, x / ay, -z / ay)
+            } else {
+                (3, x / ay, z / ay)
+            }
+        } else if z < 0.0 {
+            (4, x / az, -y / az)
+        } else {
+            (5, -x / az, -y / az)
+        };
+        if !s.is_finite() || !t.is_finite() {
+            return Color::default();
+        }
+
+        let u = 0.5 * (s + 1.0);
+        let v = 0.5 * (t + 1.0);
+        self.intensity * self.faces[face].bilinear(u, v, Wrap::Clamp, Wrap::Clamp)
+    }
+}
@@ -0,0 +1,210 @@
+// Minimal OpenEXR writer: single part scanline image with R, G and B channels,
+// stored as half or full floats, uncompressed or with ZIP compression.
+
+use std::io::{self, Write};
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::image::ImageBuffer;
+
+const MAGIC: u32 = 20000630;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExrPixelType {
+    Half,
+    Float,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ExrCompression {
+    None,
+    Zip,
+}
+
+impl ExrPixelType {
+    fn id(self) -> i32 {
+        match self {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        }
+    }
+}
+
+impl ExrCompression {
+    fn id(self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3,
+        }
+    }
+
+    fn lines_per_chunk(self) -> usize {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => 16,
+        }
+    }
+}
+
+pub fn write_exr(
+    image: &ImageBuffer,
+    pixel_type: ExrPixelType,
+    compression: ExrCompression,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let width = image.width();
+    let height = image.height();
+    let max_x = width as i32 - 1;
+    let max_y = height as i32 - 1;
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC.to_le_bytes());
+    header.extend_from_slice(&2u32.to_le_bytes()); // version 2, single part scanline
+
+    // Channels are listed in alphabetical order, and stored in that order too.
+    let mut channels = Vec::new();
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&pixel_type.id().to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        channels.extend_from_slice(&1i32.to_le_bytes()); // x sampling
+        channels.extend_from_slice(&1i32.to_le_bytes()); // y sampling
+    }
+    channels.push(0);
+    attribute(&mut header, "channels", "chlist", &channels);
+    attribute(&mut header, "compression", "compression", &[compression.id()]);
+    let window = box2i(0, 0, max_x, max_y);
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]); // increasing y
+    attribute(&mut header, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0u8; 8]);
+    attribute(&mut header, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    header.push(0);
+
+    // Each chunk holds a few scanlines; for every scanline all of B, then G, then R.
+    let lines = compression.lines_per_chunk();
+    let mut chunks = Vec::new();
+    for first_line in (0..height).step_by(lines) {
+        let mut raw = Vec::new();
+        for y in first_line..(first_line + lines).min(height) {
+            for channel in [2, 1, 0] {
+                for x in 0..width {
+                    let v = image.get(x, y)[channel] as f32;
+                    match pixel_type {
+                        ExrPixelType::Half => raw.extend_from_slice(&f32_to_half(v).to_le_bytes()),
+                        ExrPixelType::Float => raw.extend_from_slice(&v.to_le_bytes()),
+                    }
+                }
+            }
+        }
+        let data = match compression {
+            ExrCompression::None => raw,
+            ExrCompression::Zip => {
+                // Data that does not shrink is stored as is.
+                let packed = zip_compress(&raw)?;
+                if packed.len() < raw.len() {
+                    packed
+                } else {
+                    raw
+                }
+            }
+        };
+        chunks.push((first_line as i32, data));
+    }
+
+    // Offset table: where each chunk starts, counted from the start of the file.
+    let mut offset = (header.len() + 8 * chunks.len()) as u64;
+    out.write_all(&header)?;
+    for (_, data) in &chunks {
+        out.write_all(&offset.to_le_bytes())?;
+        offset += 8 + data.len() as u64;
+    }
+    for (y, data) in &chunks {
+        out.write_all(&y.to_le_bytes())?;
+        out.write_all(&(data.len() as i32).to_le_bytes())?;
+        out.write_all(data)?;
+    }
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+fn box2i(x_min: i32, y_min: i32, x_max: i32, y_max: i32) -> Vec<u8> {
+    [x_min, y_min, x_max, y_max]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+}
+
+// EXR's ZIP scheme: split the bytes into two halves (even and odd positions),
+// store each byte as the difference to the one before, then deflate.
+fn zip_compress(raw: &[u8]) -> io::Result<Vec<u8>> {
+    let half = raw.len().div_ceil(2);
+    let mut shuffled = vec![0u8; raw.len()];
+    for (i, &b) in raw.iter().enumerate() {
+        let at = if i % 2 == 0 { i / 2 } else { half + i / 2 };
+        shuffled[at] = b;
+    }
+
+    let mut prev = shuffled.first().copied().unwrap_or(0);
+    for b in shuffled.iter_mut().skip(1) {
+        let current = *b;
+        *b = current.wrapping_sub(prev).wrapping_add(128);
+        prev = current;
+    }
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&shuffled)?;
+    encoder.finish()
+}
+
+// IEEE 754 single to half precision, rounding to nearest even.
+// Values too large for a half become infinity.
+pub fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    // NaN and infinity.
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    if half_exponent <= 0 {
+        // Subnormal half, or zero.
+        if half_exponent < -10 {
+            return sign;
+        }
+        let m = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        let half_m = m >> shift;
+        let rest = m & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round_up = rest > halfway || (rest == halfway && half_m & 1 == 1);
+        return sign | (half_m + round_up as u32) as u16;
+    }
+
+    let half_m = mantissa >> 13;
+    let rest = mantissa & 0x1fff;
+    let round_up = rest > 0x1000 || (rest == 0x1000 && half_m & 1 == 1);
+    // A carry out of the mantissa correctly bumps the exponent.
+    let half = ((half_exponent as u32) << 10 | half_m) + round_up as u32;
+    sign | half as u16
+}
@@ -0,0 +1,294 @@
+// Masses that bend light. Rays are traced as photons with a position and a
+// momentum, using units where the speed of light is 1: a ray starts with a unit
+// momentum and one unit of "time" moves it about one scene unit. Away from
+// spinning black holes the momentum is simply the photon's velocity.
+
+use crate::vec3::{self, Point3, Vec3};
+
+pub const G: f64 = 6.6743e-11; // gravitational constant
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GravityModel {
+    // Newtonian pull -G * M / R^2 that only turns the ray, its speed stays 1.
+    Newtonian,
+    // Null geodesics around a non-rotating black hole. Rays that fall through the
+    // event horizon are captured.
+    Schwarzschild,
+    // Null geodesics around a rotating black hole, in Cartesian Kerr-Schild
+    // coordinates. Gives the flattened, off-center shadow and frame dragging.
+    Kerr,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GravitySource {
+    pub position: Point3,
+    pub mass: f64,
+    pub model: GravityModel,
+    // Kerr only: angular momentum as a fraction of the maximum, a / M in [0, 1),
+    // and the unit axis it turns around (counterclockwise seen from the tip).
+    pub spin: f64,
+    pub axis: Vec3,
+}
+
+impl GravitySource {
+    pub fn new(position: Point3, mass: f64) -> GravitySource {
+        GravitySource {
+            position,
+            mass,
+            model: GravityModel::Newtonian,
+            spin: 0.0,
+            axis: Vec3::new(0.0, 1.0, 0.0),
+        }
+    }
+
+    pub fn black_hole(position: Point3, mass: f64) -> GravitySource {
+        GravitySource {
+            model: GravityModel::Schwarzschild,
+            ..GravitySource::new(position, mass)
+        }
+    }
+
+    pub fn kerr(position: Point3, mass: f64, spin: f64, axis: Vec3) -> GravitySource {
+        GravitySource {
+            model: GravityModel::Kerr,
+            spin,
+            axis: vec3::unit_vector(axis),
+            ..GravitySource::new(position, mass)
+        }
+    }
+
+    // r_s = 2 * G * M / c^2, with c = 1.
+    pub fn schwarzschild_radius(&self) -> f64 {
+        2.0 * G * self.mass
+    }
+
+    // Radius of the event horizon, r_+ = M + sqrt(M^2 - a^2) for a spinning hole.
+    pub fn horizon_radius(&self) -> f64 {
+        let m = G * self.mass;
+        match self.model {
+            GravityModel::Newtonian => 0.0,
+            GravityModel::Schwarzschild => 2.0 * m,
+            GravityModel::Kerr => m + m * f64::sqrt(1.0 - self.spin * self.spin),
+        }
+    }
+
+    // Whether a photon moving from `from` to `to` crosses the event horizon, so it
+    // can never come back out.
+    pub fn captures(&self, from: Point3, to: Point3) -> bool {
+        if self.model == GravityModel::Newtonian {
+            return false;
+        }
+
+        // The closest point of the segment to the hole.
+        let d = to - from;
+        let len2 = d.length_squared();
+        let t = if len2 > 0.0 {
+            (vec3::dot(self.position - from, d) / len2).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let closest = from + t * d - self.position;
+        let r = match self.model {
+            GravityModel::Kerr => self.kerr_schild(closest).0,
+            _ => closest.length(),
+        };
+        r <= self.horizon_radius()
+    }
+
+    // Momentum of a photon leaving `pos` along the unit vector `dir`.
+    pub fn initial_momentum(&self, pos: Point3, dir: Vec3) -> Vec3 {
+        if self.model != GravityModel::Kerr {
+            return dir;
+        }
+        // Solve dx/dt ~ dir and g(p, p) = 0 for p, using |l| = 1.
+        let (_, f, l) = self.kerr_schild(pos - self.position);
+        let ld = vec3::dot(l, dir);
+        let k = 1.0 / f64::sqrt(1.0 - f * (1.0 - ld * ld));
+        let s = (1.0 + k * ld) / (1.0 - f);
+        k * dir + f * s * l
+    }
+
+    // Change of the photon position and momentum along the path, where this source
+    // adds to a free photon's (mom, 0).
+    pub fn derivative(&self, pos: Point3, mom: Vec3) -> (Vec3, Vec3) {
+        let r_vec = pos - self.position;
+        let dist = r_vec.length();
+        if dist < 1e-6 {
+            return (Vec3::default(), Vec3::default());
+        }
+        match self.model {
+            GravityModel::Newtonian => {
+                // a = -G * mass / R^2 * r_hat, without the part along the velocity.
+                let a = -G * self.mass / (dist * dist * dist) * r_vec;
+                (Vec3::default(), a - vec3::dot(a, mom) / mom.length_squared() * mom)
+            }
+            GravityModel::Schwarzschild => {
+                // In Schwarzschild coordinates, taken as Cartesian, the photon
+                // orbit equation u'' + u = 3/2 r_s u^2 (u = 1 / r) is the same as
+                // x'' = -3/2 r_s h^2 x / r^5, with h = |x × x'| the conserved
+                // angular momentum.
+                let h2 = vec3::cross(r_vec, mom).length_squared();
+                let a = -1.5 * self.schwarzschild_radius() * h2 / dist.powi(5) * r_vec;
+                (Vec3::default(), a)
+            }
+            GravityModel::Kerr => {
+                // The metric is g = η + f l l, so the photon Hamiltonian is
+                // H = (|p|^2 - 1 - f (1 + l.p)^2) / 2 with energy 1, which gives
+                // dx = p - f (1 + l.p) l and dp = ∇(f (1 + l.p)^2) / 2. The
+                // gradient is taken numerically.
+                let (_, f, l) = self.kerr_schild(r_vec);
+                let lp = 1.0 + vec3::dot(l, mom);
+                let potential = |x: Vec3| {
+                    let (_, f, l) = self.kerr_schild(x);
+                    let lp = 1.0 + vec3::dot(l, mom);
+                    f * lp * lp
+                };
+                let eps = 1e-6 * dist;
+                let grad = |e: Vec3| (potential(r_vec + eps * e) - potential(r_vec - eps * e)) / (2.0 * eps);
+                let force = 0.5
+                    * Vec3::new(
+                        grad(Vec3::new(1.0, 0.0, 0.0)),
+                        grad(Vec3::new(0.0, 1.0, 0.0)),
+                        grad(Vec3::new(0.0, 0.0, 1.0)),
+                    );
+                (-f * lp * l, force)
+            }
+        }
+    }
+
+    // Kerr-Schild radius r, factor f and spatial null vector l at `x` relative to
+    // the hole, for the metric g = η + f l l.
+    fn kerr_schild(&self, x: Vec3) -> (f64, f64, Vec3) {
+        let m = G * self.mass;
+        // Rays are traced backwards from the camera. That is the same as following
+        // them forwards in time around a hole spinning the other way, and keeps
+        // the coordinates well behaved where rays fall in.
+        let a = -self.spin * m;
+
+        // r solves (x^2 + y^2) / (r^2 + a^2) + z^2 / r^2 = 1, with z along the axis.
+        let z = vec3::dot(x, self.axis);
+        let x_perp = x - z * self.axis;
+        let w = 0.5 * (x.length_squared() - a * a);
+        let r2 = w + f64::sqrt(w * w + a * a * z * z);
+        let r = r2.sqrt();
+        if r < 1e-9 {
+            return (0.0, 0.0, Vec3::default());
+        }
+
+        let f = 2.0 * m * r2 * r / (r2 * r2 + a * a * z * z);
+        let l = (r * x_perp - a * vec3::cross(self.axis, x_perp)) / (r2 + a * a) + z / r * self.axis;
+        (r, f, l)
+    }
+}
+
+// Starting momentum of a photon leaving `pos` along `dir`.
+pub fn initial_momentum(sources: &[GravitySource], pos: Point3, dir: Vec3) -> Vec3 {
+    let dir = vec3::unit_vector(dir);
+    sources
+        .iter()
+        .fold(dir, |p, source| p + source.initial_momentum(pos, dir) - dir)
+}
+
+// Change of position and momentum with all sources added up.
+pub fn derivative(sources: &[GravitySource], pos: Point3, mom: Vec3) -> (Vec3, Vec3) {
+    sources.iter().fold((mom, Vec3::default()), |(dx, dp), source| {
+        let (ddx, ddp) = source.derivative(pos, mom);
+        (dx + ddx, dp + ddp)
+    })
+}
+
+// Direction the photon moves in.
+pub fn velocity(sources: &[GravitySource], pos: Point3, mom: Vec3) -> Vec3 {
+    derivative(sources, pos, mom).0
+}
+
+pub fn captured(sources: &[GravitySource], from: Point3, to: Point3) -> bool {
+    sources.iter().any(|source| source.captures(from, to))
+}
+
+// Dormand-Prince 5(4) coefficients: stage weights, the fifth order solution and
+// its difference to the embedded fourth order one.
+const DP_A: [[f64; 6]; 7] = [
+    [0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+    [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+    [19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0, 0.0, 0.0],
+    [9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0, 0.0],
+    [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0],
+];
+const DP_B: [f64; 7] = [35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0, 0.0];
+const DP_E: [f64; 7] = [
+    71.0 / 57600.0,
+    0.0,
+    -71.0 / 16695.0,
+    71.0 / 1920.0,
+    -17253.0 / 339200.0,
+    22.0 / 525.0,
+    -1.0 / 40.0,
+];
+
+const MIN_STEP: f64 = 1e-9;
+
+// An accepted integration step.
+#[derive(Copy, Clone, Debug)]
+pub struct Step {
+    pub pos: Point3,
+    pub mom: Vec3,
+    // Length of this step, and the one suggested for the next.
+    pub h: f64,
+    pub next_h: f64,
+}
+
+// Advance a photon by at most `h`, taking a shorter step where gravity is strong
+// so that the step stays within `tolerance` (in scene units). That covers both the
+// integration error and how far the path bulges away from the straight line
+// between its ends, as that line is what gets intersected with the scene.
+pub fn adaptive_step(sources: &[GravitySource], pos: Point3, mom: Vec3, h: f64, tolerance: f64) -> Step {
+    let mut k = [(Vec3::default(), Vec3::default()); 7];
+    k[0] = derivative(sources, pos, mom);
+
+    // The line between the ends of a step is off the curve by about h^2 * |a| / 8.
+    let mut h = h;
+    let a = k[0].1.length();
+    if a > 0.0 {
+        h = h.min(f64::sqrt(8.0 * tolerance / a)).max(MIN_STEP);
+    }
+
+    loop {
+        for stage in 1..7 {
+            let (mut x, mut p) = (pos, mom);
+            for (j, &w) in DP_A[stage][..stage].iter().enumerate() {
+                x += h * w * k[j].0;
+                p += h * w * k[j].1;
+            }
+            k[stage] = derivative(sources, x, p);
+        }
+
+        let (mut next_pos, mut next_mom) = (pos, mom);
+        let (mut pos_err, mut mom_err) = (Vec3::default(), Vec3::default());
+        for j in 0..7 {
+            next_pos += h * DP_B[j] * k[j].0;
+            next_mom += h * DP_B[j] * k[j].1;
+            pos_err += h * DP_E[j] * k[j].0;
+            mom_err += h * DP_E[j] * k[j].1;
+        }
+        let err = pos_err.length().max(mom_err.length());
+
+        // Usual step size control for a fifth order method, with some safety margin.
+        let scale = if err > 0.0 {
+            0.9 * (tolerance / err).powf(0.2)
+        } else {
+            5.0
+        };
+        if err <= tolerance || h <= MIN_STEP {
+            return Step {
+                pos: next_pos,
+                mom: next_mom,
+                h,
+                next_h: h * scale.min(5.0),
+            };
+        }
+        h = (h * scale.max(0.2)).max(MIN_STEP);
+    }
+}
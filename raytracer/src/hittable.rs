@@ -1,6 +1,8 @@
-use std::rc::Rc;
+use std::sync::Arc;
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{self, Point3, Vec3};
 
 #[derive(Clone, Default)]
@@ -8,8 +10,11 @@ pub struct HitRecord {
      pub p: Point3,
      pub normal: Vec3,
      pub t: f64,
+     // Surface coordinates of the hit, for textures.
+     pub u: f64,
+     pub v: f64,
      pub front_face: bool,
-     pub mat: Option<Rc<dyn Material>>,
+     pub mat: Option<Arc<dyn Material>>,
  }
 
  impl HitRecord {
@@ -27,6 +32,21 @@ pub struct HitRecord {
      }
  }
 
- pub trait Hittable {
+ pub trait Hittable: Send + Sync {
      fn hit(&self, ray: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool;
+
+     // Box enclosing the whole object, None if it is unbounded.
+     fn bounding_box(&self) -> Option<Aabb>;
+
+     // Light sampling: `random` picks a direction from `origin` towards a random
+     // point of the object, and `pdf_value` is the density of that choice per unit
+     // solid angle. Objects that can't be sampled keep these defaults, so lights
+     // made of them are only found by rays that happen to hit them.
+     fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+         0.0
+     }
+
+     fn random(&self, _origin: Point3, _sampler: &mut dyn Sampler) -> Vec3 {
+         Vec3::new(1.0, 0.0, 0.0)
+     }
  }
@@ -1,9 +1,15 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{Point3, Vec3};
  
 #[derive(Default)]
 pub struct HittableList {
-    objects: Vec<Box<dyn Hittable>>,
+    objects: Vec<Arc<dyn Hittable>>,
 }
  
 impl HittableList {
@@ -11,9 +17,31 @@ impl HittableList {
         Default::default()
     }
  
-    pub fn add(&mut self, object: Box<dyn Hittable>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
         self.objects.push(object);
     }
+
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
+    }
+
+    // Same objects, with every bounded one moved into a single BVH so a ray only
+    // tests the objects near its path. Unbounded objects stay in the flat list.
+    pub fn into_bvh(self) -> HittableList {
+        let (bounded, unbounded): (Vec<_>, Vec<_>) = self
+            .objects
+            .into_iter()
+            .partition(|object| object.bounding_box().is_some());
+
+        let mut list = HittableList::new();
+        if !bounded.is_empty() {
+            list.add(Arc::new(BvhNode::new(bounded)));
+        }
+        for object in unbounded {
+            list.add(object);
+        }
+        list
+    }
 }
  
 impl Hittable for HittableList {
@@ -32,4 +60,28 @@ impl Hittable for HittableList {
  
         hit_anything
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut boxes = self.objects.iter().map(|object| object.bounding_box());
+        let first = boxes.next()??;
+        boxes.try_fold(first, |acc, b| Some(Aabb::surrounding(&acc, &b?)))
+    }
+
+    // As a set of lights: each object is picked with the same chance.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self.objects.iter().map(|object| object.pdf_value(origin, direction)).sum();
+        sum / self.objects.len() as f64
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let n = self.objects.len();
+        if n == 0 {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = ((sampler.next_1d() * n as f64) as usize).min(n - 1);
+        self.objects[index].random(origin, sampler)
+    }
 }
@@ -0,0 +1,93 @@
+use crate::color::Color;
+
+// What lookups outside [0, 1] do.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Wrap {
+    // Tile the image.
+    Repeat,
+    // Use the nearest edge pixel.
+    Clamp,
+}
+
+// Rendered image in linear color, not clamped, top row first.
+#[derive(Clone)]
+pub struct ImageBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl ImageBuffer {
+    pub fn new(width: usize, height: usize) -> ImageBuffer {
+        ImageBuffer {
+            width,
+            height,
+            pixels: vec![Color::default(); width * height],
+        }
+    }
+
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> ImageBuffer {
+        assert_eq!(pixels.len(), width * height, "pixel count does not match size");
+        ImageBuffer {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, c: Color) {
+        self.pixels[y * self.width + x] = c;
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Color]> {
+        self.pixels.chunks(self.width)
+    }
+
+    // Bilinear lookup at (u, v), where (0, 0) is the top left corner of the image
+    // and (1, 1) the bottom right one.
+    pub fn bilinear(&self, u: f64, v: f64, wrap_u: Wrap, wrap_v: Wrap) -> Color {
+        // Pixel centers are at half integers.
+        let x = u * self.width as f64 - 0.5;
+        let y = v * self.height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let xs = [wrap(x0 as i64, self.width, wrap_u), wrap(x0 as i64 + 1, self.width, wrap_u)];
+        let ys = [wrap(y0 as i64, self.height, wrap_v), wrap(y0 as i64 + 1, self.height, wrap_v)];
+        let top = (1.0 - fx) * self.get(xs[0], ys[0]) + fx * self.get(xs[1], ys[0]);
+        let bottom = (1.0 - fx) * self.get(xs[0], ys[1]) + fx * self.get(xs[1], ys[1]);
+        (1.0 - fy) * top + fy * bottom
+    }
+}
+
+fn wrap(i: i64, size: usize, mode: Wrap) -> usize {
+    match mode {
+        Wrap::Repeat => i.rem_euclid(size as i64) as usize,
+        Wrap::Clamp => i.clamp(0, size as i64 - 1) as usize,
+    }
+}
+
+// Display-ready image: integer RGB values in [0, max_value], top row first.
+#[derive(Clone)]
+pub struct LdrImage {
+    pub width: usize,
+    pub height: usize,
+    pub max_value: u16,
+    pub data: Vec<u16>,
+}
@@ -0,0 +1,476 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::color::Color;
+use crate::exr::{self, ExrCompression, ExrPixelType};
+use crate::image::{ImageBuffer, LdrImage};
+use crate::tonemap::{self, DisplayTransform};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    // ASCII PPM
+    P3,
+    // Binary PPM
+    P6,
+    // PNG with 8 bits per channel
+    Png8,
+    // PNG with 16 bits per channel
+    Png16,
+    // Radiance RGBE, linear and unclamped
+    Hdr,
+    // Portable FloatMap, linear and unclamped
+    Pfm,
+    // OpenEXR, linear and unclamped
+    Exr(ExrPixelType, ExrCompression),
+}
+
+impl ImageFormat {
+    // Format implied by a file extension, if any.
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "ppm" => Some(ImageFormat::P3),
+            "png" => Some(ImageFormat::Png8),
+            "hdr" => Some(ImageFormat::Hdr),
+            "pfm" => Some(ImageFormat::Pfm),
+            "exr" => Some(ImageFormat::Exr(ExrPixelType::Half, ExrCompression::Zip)),
+            _ => None,
+        }
+    }
+}
+
+// LDR formats go through `display` first, HDR formats store the linear values.
+pub fn write_image(
+    image: &ImageBuffer,
+    format: ImageFormat,
+    display: &DisplayTransform,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    match format {
+        ImageFormat::P3 => write_p3(&display.to_ldr(image, 255), out),
+        ImageFormat::P6 => write_p6(&display.to_ldr(image, 255), out),
+        ImageFormat::Png8 => write_png(&display.to_ldr(image, 255), out),
+        ImageFormat::Png16 => write_png(&display.to_ldr(image, 65535), out),
+        ImageFormat::Hdr => write_hdr(image, out),
+        ImageFormat::Pfm => write_pfm(image, out),
+        ImageFormat::Exr(pixel_type, compression) => exr::write_exr(image, pixel_type, compression, out),
+    }
+}
+
+pub fn write_p3(image: &LdrImage, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "P3\n{} {}\n{}\n", image.width, image.height, image.max_value)?;
+    for rgb in image.data.chunks(3) {
+        writeln!(out, "{} {} {}", rgb[0], rgb[1], rgb[2])?;
+    }
+    Ok(())
+}
+
+// 8 bit samples are one byte, 16 bit ones two bytes, most significant first.
+pub fn write_p6(image: &LdrImage, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n{}\n", image.width, image.height, image.max_value)?;
+    out.write_all(&sample_bytes(image))
+}
+
+pub fn write_png(image: &LdrImage, out: &mut impl Write) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(if image.max_value > 255 {
+        png::BitDepth::Sixteen
+    } else {
+        png::BitDepth::Eight
+    });
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&sample_bytes(image)).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+fn sample_bytes(image: &LdrImage) -> Vec<u8> {
+    if image.max_value > 255 {
+        image.data.iter().flat_map(|v| v.to_be_bytes()).collect()
+    } else {
+        image.data.iter().map(|&v| v as u8).collect()
+    }
+}
+
+pub fn write_hdr(image: &ImageBuffer, out: &mut impl Write) -> io::Result<()> {
+    write!(
+        out,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        image.height(),
+        image.width()
+    )?;
+    for row in image.rows() {
+        let rgbe: Vec<[u8; 4]> = row.iter().map(|&c| to_rgbe(c)).collect();
+        write_rgbe_scanline(&rgbe, out)?;
+    }
+    Ok(())
+}
+
+// Shared 8 bit mantissas for r, g and b plus one exponent.
+fn to_rgbe(c: Color) -> [u8; 4] {
+    // Anything brighter than the format can hold (including infinity) is written
+    // as the brightest value; NaN and negative values as black.
+    let max = 255.0 / 256.0 * f64::powi(2.0, 127);
+    let clean = |x: f64| if x.is_nan() { 0.0 } else { x.clamp(0.0, max) };
+    let (r, g, b) = (clean(c.x()), clean(c.y()), clean(c.z()));
+
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // v = m * 2^e with m in [0.5, 1).
+    let e = (v.log2().floor() as i32 + 1).clamp(-128, 127);
+    let scale = 256.0 / f64::powi(2.0, e);
+    let channel = |x: f64| (x * scale).min(255.0) as u8;
+    [channel(r), channel(g), channel(b), (e + 128) as u8]
+}
+
+// Adaptive run length encoding: each of the four components is stored separately
+// as runs of equal bytes and literal dumps. Scanlines outside the range the format
+// can encode are written flat.
+fn write_rgbe_scanline(rgbe: &[[u8; 4]], out: &mut impl Write) -> io::Result<()> {
+    let width = rgbe.len();
+    if !(8..0x8000).contains(&width) {
+        for pixel in rgbe {
+            out.write_all(pixel)?;
+        }
+        return Ok(());
+    }
+
+    out.write_all(&[2, 2, (width >> 8) as u8, (width & 0xff) as u8])?;
+    for component in 0..4 {
+        let bytes: Vec<u8> = rgbe.iter().map(|p| p[component]).collect();
+        let mut i = 0;
+        while i < width {
+            // Length of the run of equal bytes starting at i.
+            let run = bytes[i..]
+                .iter()
+                .take(127)
+                .take_while(|&&b| b == bytes[i])
+                .count();
+            if run >= 3 {
+                out.write_all(&[128 + run as u8, bytes[i]])?;
+                i += run;
+                continue;
+            }
+
+            // Literal bytes up to the next run of at least 3.
+            let mut end = i;
+            while end < width && end - i < 128 {
+                if end + 2 < width && bytes[end] == bytes[end + 1] && bytes[end] == bytes[end + 2] {
+                    break;
+                }
+                end += 1;
+            }
+            out.write_all(&[(end - i) as u8])?;
+            out.write_all(&bytes[i..end])?;
+            i = end;
+        }
+    }
+    Ok(())
+}
+
+// Little-endian floats (negative scale), bottom row first.
+pub fn write_pfm(image: &ImageBuffer, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+    let rows: Vec<&[Color]> = image.rows().collect();
+    for row in rows.iter().rev() {
+        for c in row.iter() {
+            for v in [c.x(), c.y(), c.z()] {
+                out.write_all(&(v as f32).to_le_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Load a Radiance `.hdr`, `.pfm`, `.ppm` or `.png` image as linear color. PPM and
+// PNG values are taken to be sRGB encoded.
+pub fn read_image(path: &Path) -> io::Result<ImageBuffer> {
+    let data = fs::read(path)?;
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match ext.as_str() {
+        "hdr" => read_hdr(&data),
+        "pfm" => read_pfm(&data),
+        "ppm" | "pnm" => read_ppm(&data),
+        "png" => read_png(&data),
+        _ => Err(invalid_data(format!("unsupported image type `.{}`", ext))),
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+pub fn read_hdr(data: &[u8]) -> io::Result<ImageBuffer> {
+    let mut pos = 0;
+    let mut next_line = || -> io::Result<&[u8]> {
+        let rest = &data[pos..];
+        let len = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| invalid_data("truncated Radiance header".to_string()))?;
+        pos += len + 1;
+        Ok(&rest[..len])
+    };
+
+    let magic = next_line()?;
+    if !magic.starts_with(b"#?") {
+        return Err(invalid_data("not a Radiance file".to_string()));
+    }
+    loop {
+        let line = next_line()?;
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with(b"FORMAT=") && line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_data(format!(
+                "unsupported Radiance {}",
+                String::from_utf8_lossy(line)
+            )));
+        }
+    }
+
+    // Only the usual orientation: rows top to bottom, pixels left to right.
+    let resolution = String::from_utf8_lossy(next_line()?).into_owned();
+    let fields: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match fields.as_slice() {
+        ["-Y", h, "+X", w] => (h.parse::<usize>(), w.parse::<usize>()),
+        _ => return Err(invalid_data(format!("unsupported Radiance resolution `{}`", resolution))),
+    };
+    let (height, width) = match (height, width) {
+        (Ok(h), Ok(w)) if h > 0 && w > 0 => (h, w),
+        _ => return Err(invalid_data(format!("bad Radiance resolution `{}`", resolution))),
+    };
+
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut bytes = data[pos..].iter().copied();
+    let mut byte = || bytes.next().ok_or_else(|| invalid_data("truncated Radiance data".to_string()));
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        let head = [byte()?, byte()?, byte()?, byte()?];
+        if head[0] == 2 && head[1] == 2 && head[2] < 128 && (8..0x8000).contains(&width) {
+            if ((head[2] as usize) << 8 | head[3] as usize) != width {
+                return Err(invalid_data("Radiance scanline length mismatch".to_string()));
+            }
+            // Run length encoded, one component after the other.
+            for component in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = byte()? as usize;
+                    if count > 128 {
+                        let value = byte()?;
+                        let run = count - 128;
+                        if x + run > width {
+                            return Err(invalid_data("Radiance run past end of scanline".to_string()));
+                        }
+                        for pixel in &mut scanline[x..x + run] {
+                            pixel[component] = value;
+                        }
+                        x += run;
+                    } else {
+                        if count == 0 || x + count > width {
+                            return Err(invalid_data("bad Radiance literal run".to_string()));
+                        }
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[component] = byte()?;
+                        }
+                        x += count;
+                    }
+                }
+            }
+        } else {
+            // Flat pixels.
+            scanline[0] = head;
+            for pixel in scanline.iter_mut().skip(1) {
+                *pixel = [byte()?, byte()?, byte()?, byte()?];
+            }
+        }
+        pixels.extend(scanline.iter().map(|&p| from_rgbe(p)));
+    }
+    Ok(ImageBuffer::from_pixels(width, height, pixels))
+}
+
+fn from_rgbe(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    let scale = f64::powi(2.0, rgbe[3] as i32 - 136);
+    let channel = |m: u8| (m as f64 + 0.5) * scale;
+    Color::new(channel(rgbe[0]), channel(rgbe[1]), channel(rgbe[2]))
+}
+
+// Header fields of PPM and PFM files: whitespace separated, with `#` comments in
+// PPM. Returns the fields and where the pixel data starts.
+fn header_fields(data: &[u8], count: usize) -> io::Result<(Vec<String>, usize)> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while fields.len() < count {
+        match data.get(pos) {
+            None => return Err(invalid_data("truncated image header".to_string())),
+            Some(b'#') => {
+                while pos < data.len() && data[pos] != b'\n' {
+                    pos += 1;
+                }
+            }
+            Some(b) if b.is_ascii_whitespace() => pos += 1,
+            Some(_) => {
+                let start = pos;
+                while pos < data.len() && !data[pos].is_ascii_whitespace() {
+                    pos += 1;
+                }
+                fields.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+            }
+        }
+    }
+    // A single whitespace character separates the header from binary data.
+    Ok((fields, pos + 1))
+}
+
+fn parse_field<T: std::str::FromStr>(field: &str, what: &str) -> io::Result<T> {
+    field
+        .parse()
+        .map_err(|_| invalid_data(format!("bad {} `{}`", what, field)))
+}
+
+// Number of samples in a width x height image from a file header, checked so a
+// bad header gives an error rather than a panic.
+fn sample_count(width: usize, height: usize, channels: usize) -> io::Result<usize> {
+    if width == 0 || height == 0 {
+        return Err(invalid_data(format!("bad image size {}x{}", width, height)));
+    }
+    width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(channels))
+        .ok_or_else(|| invalid_data(format!("image size {}x{} is too large", width, height)))
+}
+
+// The `count` values of `size` bytes each that follow the header at `start`.
+fn body(data: &[u8], start: usize, count: usize, size: usize) -> io::Result<&[u8]> {
+    count
+        .checked_mul(size)
+        .and_then(|n| n.checked_add(start))
+        .and_then(|end| data.get(start..end))
+        .ok_or_else(|| invalid_data("truncated image data".to_string()))
+}
+
+pub fn read_pfm(data: &[u8]) -> io::Result<ImageBuffer> {
+    let (fields, start) = header_fields(data, 4)?;
+    let channels = match fields[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("not a PFM file".to_string())),
+    };
+    let width: usize = parse_field(&fields[1], "width")?;
+    let height: usize = parse_field(&fields[2], "height")?;
+    let scale: f64 = parse_field(&fields[3], "scale")?;
+
+    let values = sample_count(width, height, channels)?;
+    let floats: Vec<f64> = body(data, start, values, 4)?
+        .chunks_exact(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            // A negative scale means little-endian.
+            if scale < 0.0 {
+                f32::from_le_bytes(b) as f64
+            } else {
+                f32::from_be_bytes(b) as f64
+            }
+        })
+        .collect();
+
+    // Rows are stored bottom to top.
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in floats.chunks_exact(width * channels).rev() {
+        for p in row.chunks_exact(channels) {
+            pixels.push(match channels {
+                3 => Color::new(p[0], p[1], p[2]),
+                _ => Color::new(p[0], p[0], p[0]),
+            });
+        }
+    }
+    Ok(ImageBuffer::from_pixels(width, height, pixels))
+}
+
+// ASCII (P3) or binary (P6) PPM, with 8 or 16 bit samples.
+pub fn read_ppm(data: &[u8]) -> io::Result<ImageBuffer> {
+    let (fields, start) = header_fields(data, 4)?;
+    let binary = match fields[0].as_str() {
+        "P3" => false,
+        "P6" => true,
+        _ => return Err(invalid_data("not a P3 or P6 PPM file".to_string())),
+    };
+    let width: usize = parse_field(&fields[1], "width")?;
+    let height: usize = parse_field(&fields[2], "height")?;
+    let max_value: u32 = parse_field(&fields[3], "maximum value")?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data(format!("bad maximum value {}", max_value)));
+    }
+
+    let values = sample_count(width, height, 3)?;
+    let samples: Vec<u32> = if binary {
+        let size = if max_value > 255 { 2 } else { 1 };
+        body(data, start, values, size)?
+            .chunks_exact(size)
+            .map(|b| if size == 2 { u16::from_be_bytes([b[0], b[1]]) as u32 } else { b[0] as u32 })
+            .collect()
+    } else {
+        let (fields, _) = header_fields(&data[start.min(data.len())..], values)?;
+        fields
+            .iter()
+            .map(|f| parse_field(f, "sample"))
+            .collect::<io::Result<_>>()?
+    };
+
+    let decode = |v: u32| tonemap::srgb_decode(v.min(max_value) as f64 / max_value as f64);
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|p| Color::new(decode(p[0]), decode(p[1]), decode(p[2])))
+        .collect();
+    Ok(ImageBuffer::from_pixels(width, height, pixels))
+}
+
+// 8 or 16 bit PNG, in any color type. Samples are taken to be sRGB and alpha is
+// dropped.
+pub fn read_png(data: &[u8]) -> io::Result<ImageBuffer> {
+    let mut decoder = png::Decoder::new(data);
+    // Palettes and gray below 8 bits become plain 8 bit samples.
+    decoder.set_transformations(png::Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+
+    let channels = info.color_type.samples();
+    let wide = info.bit_depth == png::BitDepth::Sixteen;
+    let max_value = if wide { 65535.0 } else { 255.0 };
+    let sample = |row: &[u8], i: usize| {
+        let v = if wide {
+            u16::from_be_bytes([row[2 * i], row[2 * i + 1]]) as f64
+        } else {
+            row[i] as f64
+        };
+        tonemap::srgb_decode(v / max_value)
+    };
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in buf.chunks(info.line_size).take(height) {
+        for x in 0..width {
+            let first = x * channels;
+            pixels.push(if channels < 3 {
+                let gray = sample(row, first);
+                Color::new(gray, gray, gray)
+            } else {
+                Color::new(sample(row, first), sample(row, first + 1), sample(row, first + 2))
+            });
+        }
+    }
+    Ok(ImageBuffer::from_pixels(width, height, pixels))
+}
@@ -0,0 +1,226 @@
+use crate::color::Color;
+use crate::constants::INFINITY;
+use crate::gravity::{self, GravitySource};
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::ScatterRecord;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::scene::Scene;
+use crate::vec3::{Point3, Vec3};
+
+// How curved rays are traced through the scene's gravity.
+#[derive(Copy, Clone, Debug)]
+pub struct Marching {
+    // Total path length followed before the ray goes on in a straight line.
+    pub max_time: f64,
+    // Largest single step, even where gravity is weak.
+    pub max_step: f64,
+    // Largest error a step may make, in scene units.
+    pub tolerance: f64,
+}
+
+// Follow a ray through the gravity of `sources` and return where it ends up, or
+// None when it falls into a black hole.
+pub fn integrate_ray_path(r: &Ray, sources: &[GravitySource], march: &Marching) -> Option<Ray> {
+    let mut t = 0.0;
+
+    // Start with the ray's current origin and direction.
+    let mut pos = r.origin();
+    let mut mom = gravity::initial_momentum(sources, pos, r.direction());
+    let mut h = march.max_step;
+
+    while t < march.max_time {
+        let step = gravity::adaptive_step(sources, pos, mom, h.min(march.max_step), march.tolerance);
+        if gravity::captured(sources, pos, step.pos) {
+            return None;
+        }
+        (pos, mom, h) = (step.pos, step.mom, step.next_h);
+        t += step.h;
+    }
+
+    // Return the new ray with the updated position and direction.
+    let dir = gravity::velocity(sources, pos, mom).normalize();
+    Some(Ray::new(pos, dir))
+}
+
+// Light arriving along `r`, found by following it through `depth` bounces.
+//
+// On diffuse surfaces every bounce also aims one ray straight at a light (next
+// event estimation). Light reached both ways is weighed with multiple importance
+// sampling, using the power heuristic, so each way counts most where it is the
+// better guess. Shadow rays are straight, so with gravity only bounces find light.
+// All random choices along the path are drawn from `sampler`.
+pub fn ray_color(r: &Ray, scene: &Scene, depth: i32, march: &Marching, sampler: &mut dyn Sampler) -> Color {
+    trace(r, scene, depth, march, sampler, None)
+}
+
+// `bsdf_pdf` is the density with which the bounce before picked `r`, when light
+// sampling there could have found the same light; what `r` finds shining is then
+// weighed against that.
+fn trace(r: &Ray, scene: &Scene, depth: i32, march: &Marching, sampler: &mut dyn Sampler, bsdf_pdf: Option<f64>) -> Color {
+    if depth <= 0 {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+
+    let mut rec = HitRecord::new();
+
+    // Without gravity rays are straight, so test the whole ray at once.
+    if scene.gravity.is_empty() {
+        if scene.world.hit(r, 0.001, INFINITY, &mut rec) {
+            return scatter(r, &rec, scene, depth, march, sampler, bsdf_pdf);
+        }
+        return sky(r, scene, bsdf_pdf);
+    }
+
+    let mut pos = r.origin();
+    let mut mom = gravity::initial_momentum(&scene.gravity, pos, r.direction());
+    let mut h = march.max_step;
+    let mut t_total = 0.0;
+    // Only the start of the path needs to skip the surface it leaves from.
+    let mut t_min = 0.001;
+
+    while t_total < march.max_time {
+        let step = gravity::adaptive_step(&scene.gravity, pos, mom, h.min(march.max_step), march.tolerance);
+
+        // Check the straight segment between the ends of the step, over its whole length.
+        let chord = step.pos - pos;
+        let length = chord.length();
+        if length > 0.0 {
+            let segment = Ray::new(pos, chord / length);
+            if scene.world.hit(&segment, t_min, length, &mut rec) {
+                return scatter(&segment, &rec, scene, depth, march, sampler, None);
+            }
+            t_min = 0.0;
+        }
+
+        // Anything falling through an event horizon is black.
+        if gravity::captured(&scene.gravity, pos, step.pos) {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        (pos, mom, h) = (step.pos, step.mom, step.next_h);
+        t_total += step.h;
+    }
+
+    // Far enough along, the rest of the ray is taken as straight.
+    let rest = Ray::new(pos, gravity::velocity(&scene.gravity, pos, mom).normalize());
+    if scene.world.hit(&rest, t_min, INFINITY, &mut rec) {
+        return scatter(&rest, &rec, scene, depth, march, sampler, None);
+    }
+    sky(&rest, scene, None)
+}
+
+// Light leaving the hit point towards `r_in`: what the surface emits plus what it
+// scatters from the next bounce and, for diffuse surfaces, from a light sample.
+fn scatter(
+    r_in: &Ray,
+    rec: &HitRecord,
+    scene: &Scene,
+    depth: i32,
+    march: &Marching,
+    sampler: &mut dyn Sampler,
+    bsdf_pdf: Option<f64>,
+) -> Color {
+    let mat = rec.mat.as_ref().unwrap();
+    let mut emitted = mat.emitted(r_in, rec);
+    if let Some(pdf) = bsdf_pdf {
+        if !emitted.near_zero() {
+            emitted = power_heuristic(pdf, light_pdf(scene, r_in.origin(), r_in.direction())) * emitted;
+        }
+    }
+
+    let mut srec = ScatterRecord::default();
+    if !mat.scatter(r_in, rec, &mut srec, sampler) {
+        return emitted;
+    }
+    let pdf = match srec.pdf {
+        Some(pdf) if pdf > 0.0 && samples_lights(scene) => pdf,
+        _ => return emitted + srec.attenuation * trace(&srec.ray, scene, depth - 1, march, sampler, None),
+    };
+
+    // One ray aimed at a light, which only counts what it hits first.
+    let mut direct = Color::default();
+    let to_light = sample_light(scene, rec.p, sampler);
+    let light = light_pdf(scene, rec.p, to_light);
+    let bsdf = mat.scattering_pdf(r_in, rec, to_light);
+    if light > 0.0 && bsdf > 0.0 {
+        let weight = power_heuristic(light, bsdf);
+        direct = weight * bsdf / light * srec.attenuation * emission_along(&Ray::new(rec.p, to_light), scene);
+    }
+
+    // And one where the surface sends it.
+    let bounce = mat.scattering_pdf(r_in, rec, srec.ray.direction()) / pdf;
+    let indirect = trace(&srec.ray, scene, depth - 1, march, sampler, Some(pdf));
+    emitted + direct + bounce * srec.attenuation * indirect
+}
+
+// Light from the environment for a ray leaving the scene.
+fn sky(r: &Ray, scene: &Scene, bsdf_pdf: Option<f64>) -> Color {
+    let color = scene.environment.color(r.direction());
+    match bsdf_pdf {
+        Some(pdf) if scene.environment.is_light() => {
+            power_heuristic(pdf, light_pdf(scene, r.origin(), r.direction())) * color
+        }
+        _ => color,
+    }
+}
+
+// Light given off by whatever `r` hits first, without any bounces. The
+// environment only counts when it is one of the sampled lights; otherwise
+// bounces that leave the scene already collect all of it, at full weight.
+fn emission_along(r: &Ray, scene: &Scene) -> Color {
+    let mut rec = HitRecord::new();
+    if scene.world.hit(r, 0.001, INFINITY, &mut rec) {
+        return rec.mat.as_ref().unwrap().emitted(r, &rec);
+    }
+    if !scene.environment.is_light() {
+        return Color::new(0.0, 0.0, 0.0);
+    }
+    scene.environment.color(r.direction())
+}
+
+fn samples_lights(scene: &Scene) -> bool {
+    scene.gravity.is_empty() && (!scene.lights.objects().is_empty() || scene.environment.is_light())
+}
+
+// The scene lights and the environment each get picked with the same chance.
+fn light_count(scene: &Scene) -> usize {
+    scene.lights.objects().len() + scene.environment.is_light() as usize
+}
+
+fn sample_light(scene: &Scene, origin: Point3, sampler: &mut dyn Sampler) -> Vec3 {
+    let objects = scene.lights.objects();
+    let n = light_count(scene);
+    let index = ((sampler.next_1d() * n as f64) as usize).min(n - 1);
+    match objects.get(index) {
+        Some(light) => light.random(origin, sampler),
+        None => scene.environment.random(sampler),
+    }
+}
+
+// Density, per unit solid angle, of `sample_light` picking `direction`.
+fn light_pdf(scene: &Scene, origin: Point3, direction: Vec3) -> f64 {
+    let n = light_count(scene);
+    if n == 0 {
+        return 0.0;
+    }
+    let mut sum: f64 = scene
+        .lights
+        .objects()
+        .iter()
+        .map(|light| light.pdf_value(origin, direction))
+        .sum();
+    if scene.environment.is_light() {
+        sum += scene.environment.pdf_value(direction);
+    }
+    sum / n as f64
+}
+
+// MIS weight of a sample taken with density `a`, where another technique would
+// have picked it with density `b`.
+fn power_heuristic(a: f64, b: f64) -> f64 {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 == 0.0 {
+        return 0.0;
+    }
+    a2 / (a2 + b2)
+}
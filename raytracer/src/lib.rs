@@ -0,0 +1,34 @@
+pub mod aabb;
+pub mod accretion_disk;
+pub mod blackbody;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod constants;
+pub mod cube;
+pub mod environment;
+pub mod exr;
+pub mod gravity;
+pub mod hittable;
+pub mod hittable_list;
+pub mod image;
+pub mod image_io;
+pub mod integrator;
+pub mod material;
+pub mod mesh;
+pub mod obj;
+pub mod onb;
+pub mod perlin;
+pub mod ray;
+pub mod renderer;
+pub mod sampler;
+pub mod scene;
+pub mod scene_file;
+pub mod sphere;
+pub mod texture;
+pub mod tonemap;
+pub mod transform;
+pub mod triangle;
+pub mod vec3;
+
+pub use renderer::{RenderSettings, Renderer};
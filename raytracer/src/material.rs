@@ -1,59 +1,90 @@
+use std::sync::Arc;
+
 use crate::color::Color;
 use crate::hittable::HitRecord;
+use crate::onb::{self, Onb};
 use crate::ray::Ray;
-use crate::vec3::{self, random_in_unit_sphere};
-
-pub trait Material {
-    fn scatter(
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool;
+use crate::sampler::Sampler;
+use crate::texture::{SolidColor, Texture};
+use crate::vec3::{self, Vec3};
+
+// How light bounces off a surface, picked at random by `Material::scatter`.
+#[derive(Default)]
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub ray: Ray,
+    // Density of the direction of `ray`, per unit solid angle. None for mirror
+    // like bounces, which only go (nearly) one way, so there is no point in
+    // aiming them at lights.
+    pub pdf: Option<f64>,
+}
+
+pub trait Material: Send + Sync {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord, sampler: &mut dyn Sampler) -> bool;
+
+    // For materials whose scatter has a pdf: the density of scattering towards
+    // `direction`. The BSDF times the cosine term is the attenuation times this,
+    // which lets light sampling weigh directions it picked itself.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    // Light given off by the surface at the hit point, towards the incoming ray.
+    // Most materials don't glow.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    // Whether objects made of this are worth sampling as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
 }
 
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(a: Color) -> Lambertian {
-        Lambertian { albedo: a}
+        Lambertian::textured(Arc::new(SolidColor::new(a)))
+    }
+
+    pub fn textured(a: Arc<dyn Texture>) -> Lambertian {
+        Lambertian { albedo: a }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(
-        &self,
-        _r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let mut scatter_direction = rec.normal + vec3::random_in_unit_sphere();
-        
-        // If the random vector given is equal to the original ray they will create infinity/NaN
-        // This deletes it
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
-        }
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord, sampler: &mut dyn Sampler) -> bool {
+        // Cosine weighted, like the light a matte surface sends out.
+        let frame = Onb::new(rec.normal);
+        let (u1, u2) = sampler.next_2d();
+        let direction = frame.local(onb::cosine_hemisphere(u1, u2));
 
-        *attenuation = self.albedo;
-        *scattered = Ray::new(rec.p, scatter_direction);
+        srec.attenuation = self.albedo.value(rec.u, rec.v, rec.p);
+        srec.ray = Ray::new(rec.p, direction);
+        srec.pdf = Some(self.scattering_pdf(r_in, rec, direction));
         true
     }
-}
 
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> f64 {
+        onb::cosine_hemisphere_pdf(vec3::dot(rec.normal, vec3::unit_vector(direction)))
+    }
+}
 
 pub struct Metal {
-    albedo: Color,
+    albedo: Arc<dyn Texture>,
     fuzz: f64,
 }
 
 impl Metal {
     pub fn new(a: Color, f: f64) -> Metal {
-        Metal { 
+        Metal::textured(Arc::new(SolidColor::new(a)), f)
+    }
+
+    pub fn textured(a: Arc<dyn Texture>, f: f64) -> Metal {
+        Metal {
             albedo: a,
             fuzz: if f < 1.0 { f } else { 1.0 },
         }
@@ -61,17 +92,92 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter (
-        &self,
-        r_in: &Ray,
-        rec: &HitRecord,
-        attenuation: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord, sampler: &mut dyn Sampler) -> bool {
         let reflected = vec3::reflect(vec3::unit_vector(r_in.direction()), rec.normal);
+        let (u1, u2) = sampler.next_2d();
+        let fuzz = onb::in_unit_sphere(u1, u2, sampler.next_1d());
+
+        srec.attenuation = self.albedo.value(rec.u, rec.v, rec.p);
+        srec.ray = Ray::new(rec.p, reflected + self.fuzz * fuzz);
+        srec.pdf = None;
+        vec3::dot(srec.ray.direction(), rec.normal) > 0.0
+    }
+}
+
+pub struct Dielectric {
+    ir: f64, // Index of Refraction
+}
+
+impl Dielectric {
+    pub fn new(index_of_refraction: f64) -> Dielectric {
+        Dielectric {
+            ir: index_of_refraction,
+        }
+    }
+
+    // Schlick's approximation for the Fresnel reflectance.
+    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
+        let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
+        let r0 = r0 * r0;
+        r0 + (1.0 - r0) * f64::powi(1.0 - cosine, 5)
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, srec: &mut ScatterRecord, sampler: &mut dyn Sampler) -> bool {
+        // Glass absorbs nothing.
+        srec.attenuation = Color::new(1.0, 1.0, 1.0);
+        srec.pdf = None;
+        let refraction_ratio = if rec.front_face {
+            1.0 / self.ir
+        } else {
+            self.ir
+        };
+
+        let unit_direction = vec3::unit_vector(r_in.direction());
+        let cos_theta = f64::min(vec3::dot(-unit_direction, rec.normal), 1.0);
+        let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
+
+        // Past the critical angle there is no refracted ray (total internal reflection).
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+        let direction = if cannot_refract
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > sampler.next_1d()
+        {
+            vec3::reflect(unit_direction, rec.normal)
+        } else {
+            vec3::refract(unit_direction, rec.normal, refraction_ratio)
+        };
+
+        srec.ray = Ray::new(rec.p, direction);
+        true
+    }
+}
+
+// Matte surface that emits light and does not reflect any.
+pub struct DiffuseLight {
+    emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> DiffuseLight {
+        DiffuseLight::textured(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn textured(emit: Arc<dyn Texture>) -> DiffuseLight {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _srec: &mut ScatterRecord, _sampler: &mut dyn Sampler) -> bool {
+        false
+    }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
 
-        *attenuation = self.albedo;
-        *scattered = Ray::new(rec.p, reflected + self.fuzz * vec3::random_in_unit_sphere());
-        vec3::dot(scattered.direction(), rec.normal) > 0.0
+    fn emitted(&self, _r_in: &Ray, rec: &HitRecord) -> Color {
+        self.emit.value(rec.u, rec.v, rec.p)
     }
 }
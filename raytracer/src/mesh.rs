@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::triangle::Triangle;
+use crate::vec3::{Point3, Vec3};
+
+// One triangle of a mesh, as indices into the mesh's buffers.
+#[derive(Copy, Clone, Debug)]
+pub struct Face {
+    pub positions: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+    pub uvs: Option<[usize; 3]>,
+    pub material: usize,
+}
+
+// Vertex buffers shared by all the triangles of a mesh.
+#[derive(Default)]
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<(f64, f64)>,
+    pub faces: Vec<Face>,
+    pub materials: Vec<Arc<dyn Material>>,
+}
+
+// Triangle mesh with its own BVH over the faces.
+pub struct Mesh {
+    data: Arc<MeshData>,
+    bvh: BvhNode,
+}
+
+impl Mesh {
+    // Panics if `data` has no faces.
+    pub fn new(data: MeshData) -> Mesh {
+        let data = Arc::new(data);
+        let triangles: Vec<Arc<dyn Hittable>> = (0..data.faces.len())
+            .map(|face| Arc::new(Triangle::in_mesh(data.clone(), face)) as Arc<dyn Hittable>)
+            .collect();
+        Mesh {
+            bvh: BvhNode::new(triangles),
+            data,
+        }
+    }
+
+    pub fn data(&self) -> &MeshData {
+        &self.data
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        self.bvh.hit(r, t_min, t_max, rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+}
@@ -0,0 +1,184 @@
+// Wavefront OBJ loader.
+//
+// Reads vertex positions (`v`), normals (`vn`), texture coordinates (`vt`) and
+// faces (`f`). Polygons with more than three vertices are split into a fan of
+// triangles. `usemtl` switches to the scene material of the same name; faces
+// before the first `usemtl`, or naming a material the scene does not have, use
+// the default material. Everything else (groups, smoothing, `mtllib`, ...) is
+// ignored.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::material::Material;
+use crate::mesh::{Face, Mesh, MeshData};
+use crate::vec3::Vec3;
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(PathBuf, io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            ObjError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+pub fn load_obj(
+    path: &Path,
+    default_material: Arc<dyn Material>,
+    materials: &BTreeMap<String, Arc<dyn Material>>,
+) -> Result<Mesh, ObjError> {
+    let src = fs::read_to_string(path).map_err(|e| ObjError::Io(path.to_path_buf(), e))?;
+    parse_obj(&src, default_material, materials)
+}
+
+pub fn parse_obj(
+    src: &str,
+    default_material: Arc<dyn Material>,
+    materials: &BTreeMap<String, Arc<dyn Material>>,
+) -> Result<Mesh, ObjError> {
+    let mut data = MeshData {
+        materials: vec![default_material],
+        ..Default::default()
+    };
+    // Index into `data.materials` for each scene material used so far.
+    let mut material_slots: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut current_material = 0;
+
+    for (index, raw_line) in src.lines().enumerate() {
+        let line = index + 1;
+        let err = |message: String| ObjError::Parse { line, message };
+
+        let content = raw_line.split('#').next().unwrap_or("");
+        let mut tokens = content.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = parse_floats(&args, 3, "v").map_err(err)?;
+                data.positions.push(Vec3::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = parse_floats(&args, 3, "vn").map_err(err)?;
+                data.normals.push(Vec3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v, _] = parse_floats(&args, 2, "vt").map_err(err)?;
+                data.uvs.push((u, v));
+            }
+            "usemtl" => {
+                let name = args.first().copied().unwrap_or("");
+                current_material = match materials.get(name) {
+                    Some(mat) => *material_slots.entry(name).or_insert_with(|| {
+                        data.materials.push(mat.clone());
+                        data.materials.len() - 1
+                    }),
+                    None => 0,
+                };
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(err(format!("face needs at least 3 vertices, got {}", args.len())));
+                }
+                let corners = args
+                    .iter()
+                    .map(|corner| parse_corner(corner, &data))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(err)?;
+
+                // Normals and UVs are only used when every corner has one.
+                let has_normals = corners.iter().all(|c| c.1.is_some());
+                let has_uvs = corners.iter().all(|c| c.2.is_some());
+
+                // Fan triangulation around the first corner.
+                for i in 1..corners.len() - 1 {
+                    let tri = [corners[0], corners[i], corners[i + 1]];
+                    data.faces.push(Face {
+                        positions: tri.map(|c| c.0),
+                        normals: has_normals.then(|| tri.map(|c| c.1.unwrap())),
+                        uvs: has_uvs.then(|| tri.map(|c| c.2.unwrap())),
+                        material: current_material,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if data.faces.is_empty() {
+        return Err(ObjError::Parse {
+            line: src.lines().count(),
+            message: "file has no faces".to_string(),
+        });
+    }
+    Ok(Mesh::new(data))
+}
+
+// Parse `min_count` to 3 numbers, missing optional ones are 0.
+fn parse_floats(args: &[&str], min_count: usize, keyword: &str) -> Result<[f64; 3], String> {
+    if args.len() < min_count {
+        return Err(format!(
+            "`{}` needs {} numbers, got {}",
+            keyword,
+            min_count,
+            args.len()
+        ));
+    }
+    let mut values = [0.0; 3];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg
+            .parse()
+            .map_err(|_| format!("`{}` is not a number", arg))?;
+    }
+    Ok(values)
+}
+
+type Corner = (usize, Option<usize>, Option<usize>);
+
+// A face corner is `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+fn parse_corner(corner: &str, data: &MeshData) -> Result<Corner, String> {
+    let mut parts = corner.split('/');
+    let position = parts.next().unwrap_or("");
+    let uv = parts.next().filter(|s| !s.is_empty());
+    let normal = parts.next().filter(|s| !s.is_empty());
+
+    let position = resolve_index(position, data.positions.len(), "vertex")?;
+    let uv = uv
+        .map(|i| resolve_index(i, data.uvs.len(), "texture coordinate"))
+        .transpose()?;
+    let normal = normal
+        .map(|i| resolve_index(i, data.normals.len(), "normal"))
+        .transpose()?;
+    Ok((position, normal, uv))
+}
+
+// OBJ indices start at 1, negative ones count back from the latest element.
+fn resolve_index(s: &str, count: usize, what: &str) -> Result<usize, String> {
+    let i: i64 = s
+        .parse()
+        .map_err(|_| format!("`{}` is not a valid {} index", s, what))?;
+    let resolved = if i > 0 { i - 1 } else { count as i64 + i };
+    if i == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!(
+            "{} index {} out of range ({} defined so far)",
+            what, i, count
+        ));
+    }
+    Ok(resolved as usize)
+}
@@ -0,0 +1,117 @@
+// Orthonormal bases, and the sample warps that are built in them.
+//
+// The warps turn uniform numbers in [0, 1) into points of a distribution, so they
+// work with any source of random numbers. Directions come out in the local frame
+// with z up; `Onb::local` turns them to face along a normal or an axis. Each warp
+// has a matching density for importance sampling.
+
+use crate::constants::PI;
+use crate::vec3::{self, Vec3};
+
+// Right handed frame around the unit vector `w`.
+#[derive(Copy, Clone, Debug)]
+pub struct Onb {
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+}
+
+impl Onb {
+    pub fn new(w: Vec3) -> Onb {
+        let w = vec3::unit_vector(w);
+        // Any vector not too close to w will do to start the frame.
+        let helper = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = vec3::unit_vector(vec3::cross(w, helper));
+        let u = vec3::cross(v, w);
+        Onb { u, v, w }
+    }
+
+    pub fn u(&self) -> Vec3 {
+        self.u
+    }
+
+    pub fn v(&self) -> Vec3 {
+        self.v
+    }
+
+    pub fn w(&self) -> Vec3 {
+        self.w
+    }
+
+    // `a` given in this frame, in world coordinates.
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
+
+// Uniform on the surface of the unit sphere.
+pub fn on_unit_sphere(u1: f64, u2: f64) -> Vec3 {
+    let z = 1.0 - 2.0 * u1;
+    let r = f64::sqrt((1.0 - z * z).max(0.0));
+    let phi = 2.0 * PI * u2;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn on_unit_sphere_pdf() -> f64 {
+    1.0 / (4.0 * PI)
+}
+
+// Uniform inside the unit ball.
+pub fn in_unit_sphere(u1: f64, u2: f64, u3: f64) -> Vec3 {
+    u3.cbrt() * on_unit_sphere(u1, u2)
+}
+
+// Uniform inside the unit disk in the xy plane. Shirley and Chiu's concentric
+// map, which keeps nearby inputs nearby so stratified samples stay spread out.
+pub fn in_unit_disk(u1: f64, u2: f64) -> Vec3 {
+    let (a, b) = (2.0 * u1 - 1.0, 2.0 * u2 - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::default();
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+// Uniform over the hemisphere around +z.
+pub fn uniform_hemisphere(u1: f64, u2: f64) -> Vec3 {
+    let z = u1;
+    let r = f64::sqrt((1.0 - z * z).max(0.0));
+    let phi = 2.0 * PI * u2;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_hemisphere_pdf() -> f64 {
+    1.0 / (2.0 * PI)
+}
+
+// Hemisphere around +z, weighted by the cosine to the axis: the disk, lifted up.
+pub fn cosine_hemisphere(u1: f64, u2: f64) -> Vec3 {
+    let d = in_unit_disk(u1, u2);
+    let z = f64::sqrt((1.0 - d.length_squared()).max(0.0));
+    Vec3::new(d.x(), d.y(), z)
+}
+
+pub fn cosine_hemisphere_pdf(cos_theta: f64) -> f64 {
+    cos_theta.max(0.0) / PI
+}
+
+// Uniform over the directions within an angle of +z whose cosine is
+// `cos_theta_max`.
+pub fn uniform_cone(u1: f64, u2: f64, cos_theta_max: f64) -> Vec3 {
+    let z = 1.0 + u1 * (cos_theta_max - 1.0);
+    let r = f64::sqrt((1.0 - z * z).max(0.0));
+    let phi = 2.0 * PI * u2;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+pub fn uniform_cone_pdf(cos_theta_max: f64) -> f64 {
+    1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+}
@@ -0,0 +1,94 @@
+// Perlin gradient noise, with random unit gradients on the integer lattice and
+// shuffled tables to hash lattice points to them.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::vec3::{self, Point3, Vec3};
+
+const POINT_COUNT: usize = 256;
+
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    // The same seed always gives the same noise, so renders can be repeated.
+    pub fn new(seed: u64) -> Perlin {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                let v = Vec3::new(
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1.0..1.0),
+                    rng.random_range(-1.0..1.0),
+                );
+                let len2 = v.length_squared();
+                if len2 > 1e-6 && len2 <= 1.0 {
+                    break vec3::unit_vector(v);
+                }
+            })
+            .collect();
+        let mut permutation = || {
+            let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+            p.shuffle(&mut rng);
+            p
+        };
+        Perlin {
+            perm_x: permutation(),
+            perm_y: permutation(),
+            perm_z: permutation(),
+            gradients,
+        }
+    }
+
+    // Smooth noise in about [-1, 1], zero on the lattice points.
+    pub fn noise(&self, p: Point3) -> f64 {
+        let (fx, fy, fz) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (u, v, w) = (p.x() - fx, p.y() - fy, p.z() - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+        let index = |n: i64| n.rem_euclid(POINT_COUNT as i64) as usize;
+
+        // Hermite smoothing keeps the blend free of grid artifacts.
+        let (uu, vv, ww) = (smooth(u), smooth(v), smooth(w));
+        let mut sum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let g = self.gradients[self.perm_x[index(i + di)]
+                        ^ self.perm_y[index(j + dj)]
+                        ^ self.perm_z[index(k + dk)]];
+                    let (a, b, c) = (di as f64, dj as f64, dk as f64);
+                    let offset = Vec3::new(u - a, v - b, w - c);
+                    sum += (a * uu + (1.0 - a) * (1.0 - uu))
+                        * (b * vv + (1.0 - b) * (1.0 - vv))
+                        * (c * ww + (1.0 - c) * (1.0 - ww))
+                        * vec3::dot(g, offset);
+                }
+            }
+        }
+        sum
+    }
+
+    // Sum of `depth` octaves of noise, each at twice the frequency and half the
+    // weight of the one before. Always positive.
+    pub fn turbulence(&self, p: Point3, depth: u32) -> f64 {
+        let mut sum = 0.0;
+        let mut p = p;
+        let mut weight = 1.0;
+        for _ in 0..depth {
+            sum += weight * self.noise(p);
+            weight *= 0.5;
+            p = 2.0 * p;
+        }
+        sum.abs()
+    }
+}
+
+fn smooth(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,192 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::color::Color;
+use crate::image::ImageBuffer;
+use crate::integrator::{self, Marching};
+use crate::sampler::SamplerKind;
+use crate::scene::Scene;
+
+// Everything that controls how an image is rendered (as opposed to what is in it).
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    pub image_width: i32,
+    pub image_height: i32,
+    pub samples_per_pixel: i32,
+    pub max_depth: i32,
+
+    // Gravity
+    pub max_step: f64,  // Longest step when tracing curved rays.
+    pub tolerance: f64, // Largest error per step, in scene units.
+    pub max_time: f64,  // Total simulation time
+
+    // Where the random numbers come from, and the seed they are derived from. The
+    // same seed gives the same image no matter how many threads are used.
+    pub sampler: SamplerKind,
+    pub seed: u64,
+    // Number of worker threads, 0 means one per core.
+    pub threads: usize,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        let aspect_ratio = 16.0 / 9.0;
+        let image_width = 1920;
+        RenderSettings {
+            image_width,
+            image_height: (image_width as f64 / aspect_ratio) as i32,
+            samples_per_pixel: 512,
+            max_depth: 15,
+            max_step: 1.0,
+            tolerance: 1e-4,
+            max_time: 10.0,
+            sampler: SamplerKind::Independent,
+            seed: 0,
+            threads: 0,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub fn aspect_ratio(&self) -> f64 {
+        self.image_width as f64 / self.image_height as f64
+    }
+
+    pub fn marching(&self) -> Marching {
+        Marching {
+            max_time: self.max_time,
+            max_step: self.max_step,
+            tolerance: self.tolerance,
+        }
+    }
+}
+
+pub struct Renderer {
+    settings: RenderSettings,
+    // Worker threads, started once and used for every render. None when
+    // rendering on the calling thread alone.
+    pool: Option<ThreadPool>,
+}
+
+impl Renderer {
+    // Fails if the render threads cannot be started.
+    pub fn new(settings: RenderSettings) -> Result<Renderer, ThreadPoolBuildError> {
+        let pool = match settings.threads {
+            1 => None,
+            threads => Some(ThreadPoolBuilder::new().num_threads(threads).build()?),
+        };
+        Ok(Renderer { settings, pool })
+    }
+
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    // Trace every pixel of the image. The buffer holds the average of the samples
+    // of each pixel, in linear color. Progress is reported on stderr.
+    pub fn render(&self, scene: &Scene) -> ImageBuffer {
+        let s = &self.settings;
+        let scale = 1.0 / s.samples_per_pixel as f64;
+        let pixels = self
+            .render_rows(scene)
+            .into_iter()
+            .flatten()
+            .map(|pixel_color| pixel_color * scale)
+            .collect();
+        eprint!("Done");
+        ImageBuffer::from_pixels(s.image_width as usize, s.image_height as usize, pixels)
+    }
+
+    // Summed samples for every pixel, top scanline first.
+    // Scanlines are handed out to the thread pool one at a time, so threads that
+    // finish cheap rows early steal the remaining work.
+    pub fn render_rows(&self, scene: &Scene) -> Vec<Vec<Color>> {
+        let s = &self.settings;
+        let progress = Progress::new(s.image_height as usize);
+
+        let pool = match &self.pool {
+            Some(pool) => pool,
+            None => {
+                return (0..s.image_height)
+                    .rev()
+                    .map(|j| {
+                        let row = self.render_scanline(scene, j);
+                        progress.scanline_done();
+                        row
+                    })
+                    .collect();
+            }
+        };
+
+        pool.install(|| {
+            (0..s.image_height)
+                .into_par_iter()
+                .rev()
+                .map(|j| {
+                    let row = self.render_scanline(scene, j);
+                    progress.scanline_done();
+                    row
+                })
+                .collect()
+        })
+    }
+
+    pub fn render_scanline(&self, scene: &Scene, j: i32) -> Vec<Color> {
+        (0..self.settings.image_width)
+            .map(|i| self.render_pixel(scene, i, j))
+            .collect()
+    }
+
+    // Sum of all samples for pixel (i, j), with j counting up from the bottom row.
+    pub fn render_pixel(&self, scene: &Scene, i: i32, j: i32) -> Color {
+        let s = &self.settings;
+        let mut sampler = s.sampler.new_sampler(s.seed, s.samples_per_pixel as u32);
+
+        let march = s.marching();
+        let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+        for sample in 0..s.samples_per_pixel {
+            sampler.start_sample(i as u32, j as u32, sample as u32);
+            let (du, dv) = sampler.next_2d();
+            let u = (i as f64 + du) / (s.image_width - 1) as f64;
+            let v = (j as f64 + dv) / (s.image_height - 1) as f64;
+            let r = scene.camera.get_ray(u, v, sampler.as_mut());
+
+            pixel_color += integrator::ray_color(&r, scene, s.max_depth, &march, sampler.as_mut());
+        }
+        pixel_color
+    }
+}
+
+// Scanline counter shared by the render threads, used for the ETA on stderr.
+struct Progress {
+    start: Instant,
+    total: usize,
+    done: AtomicUsize,
+}
+
+impl Progress {
+    fn new(total: usize) -> Progress {
+        Progress {
+            start: Instant::now(),
+            total,
+            done: AtomicUsize::new(0),
+        }
+    }
+
+    fn scanline_done(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let remaining = self.total - done;
+
+        // Average time per scanline so far, times the scanlines still to go.
+        let avg_time = self.start.elapsed() / done as u32;
+        let estimated_remaining = avg_time * remaining as u32;
+
+        eprint!(
+            "Scanlines remaining: {}. Estimated time remaining: {:?}\r",
+            remaining, estimated_remaining
+        );
+    }
+}
@@ -0,0 +1,460 @@
+// Where the random numbers of a render come from.
+//
+// Every camera sample asks its sampler for numbers in [0, 1) one dimension at a
+// time: the pixel position, the lens, then a few per bounce. The numbers only
+// depend on the seed, the pixel, the sample index and how many were drawn before,
+// so a render comes out bit for bit the same no matter which thread traced which
+// pixel, or in what order.
+//
+// The samplers differ in how the samples of one pixel are spread out. Independent
+// numbers clump; the others place them more evenly, which makes the noise at a
+// given sample count lower, or (for blue noise) less visible.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::constants;
+
+pub trait Sampler {
+    // Starts sample `index` of pixel (x, y), back at the first dimension.
+    fn start_sample(&mut self, x: u32, y: u32, index: u32);
+
+    fn next_1d(&mut self) -> f64;
+
+    // Two dimensions that are spread out together, for picking points on
+    // surfaces and directions.
+    fn next_2d(&mut self) -> (f64, f64);
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SamplerKind {
+    // Plain random numbers.
+    #[default]
+    Independent,
+    // Each dimension cut into one stratum per sample, with a random point in it.
+    Stratified,
+    // Owen scrambled Halton sequence, one prime base per dimension.
+    Halton,
+    // Owen scrambled Sobol points, shuffled separately for each dimension.
+    Sobol,
+    // One Sobol sequence shared out along a Z curve over the image, so
+    // neighbouring pixels make different errors and the noise is fine grained.
+    BlueNoise,
+}
+
+impl SamplerKind {
+    // Sampler for pixels taking `samples_per_pixel` samples each.
+    pub fn new_sampler(self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(seed, samples_per_pixel)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+            SamplerKind::BlueNoise => Box::new(BlueNoiseSampler::new(seed, samples_per_pixel)),
+        }
+    }
+}
+
+// Largest f64 below 1.
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+fn pixel_seed(seed: u64, x: u32, y: u32) -> u64 {
+    constants::mix_seed(seed, (y as u64) << 32 | x as u64)
+}
+
+fn sample_seed(seed: u64, x: u32, y: u32, index: u32) -> u64 {
+    constants::mix_seed(pixel_seed(seed, x, y), index as u64)
+}
+
+pub struct IndependentSampler {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> IndependentSampler {
+        IndependentSampler {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.rng = StdRng::seed_from_u64(sample_seed(self.seed, x, y, index));
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        self.rng.random()
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+// Jittered sampling. In 1D the samples of a pixel each get one of
+// `samples` equal strata, in 2D one cell of a grid about as wide as it is tall.
+// Which sample gets which stratum is shuffled differently for every dimension,
+// so the dimensions don't line up with each other.
+pub struct StratifiedSampler {
+    seed: u64,
+    samples: u32,
+    // Grid for 2D strata. Samples past nx * ny (when the count isn't a product of
+    // two close numbers) fall anywhere.
+    nx: u32,
+    ny: u32,
+    pixel_seed: u64,
+    index: u32,
+    dimension: u64,
+    rng: StdRng,
+}
+
+impl StratifiedSampler {
+    pub fn new(seed: u64, samples: u32) -> StratifiedSampler {
+        let samples = samples.max(1);
+        let nx = (samples as f64).sqrt() as u32;
+        StratifiedSampler {
+            seed,
+            samples,
+            nx,
+            ny: samples / nx,
+            pixel_seed: 0,
+            index: 0,
+            dimension: 0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // The stratum this sample gets in the next dimension.
+    fn stratum(&mut self) -> u32 {
+        let shuffle = constants::mix_seed(self.pixel_seed, self.dimension) as u32;
+        self.dimension += 1;
+        permute(self.index % self.samples, self.samples, shuffle)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_seed = pixel_seed(self.seed, x, y);
+        self.index = index;
+        self.dimension = 0;
+        self.rng = StdRng::seed_from_u64(constants::mix_seed(self.pixel_seed, index as u64));
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let stratum = self.stratum();
+        let jitter: f64 = self.rng.random();
+        ((stratum as f64 + jitter) / self.samples as f64).min(ONE_MINUS_EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let stratum = self.stratum();
+        let (jx, jy): (f64, f64) = (self.rng.random(), self.rng.random());
+        if stratum >= self.nx * self.ny {
+            return (jx, jy);
+        }
+        let x = ((stratum % self.nx) as f64 + jx) / self.nx as f64;
+        let y = ((stratum / self.nx) as f64 + jy) / self.ny as f64;
+        (x.min(ONE_MINUS_EPSILON), y.min(ONE_MINUS_EPSILON))
+    }
+}
+
+// Dimensions past the last prime get independent random numbers; by then a path
+// has bounced so often that it hardly matters.
+const PRIMES: [u32; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97, 101,
+    103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193, 197, 199,
+    211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307, 311,
+];
+
+// Halton sequence, the radical inverse of the sample index in a different prime
+// base for each dimension. The digits are scrambled per pixel, which keeps the
+// samples stratified but makes every pixel use different points.
+pub struct HaltonSampler {
+    seed: u64,
+    pixel_seed: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> HaltonSampler {
+        HaltonSampler {
+            seed,
+            pixel_seed: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_seed = pixel_seed(self.seed, x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let hash = constants::mix_seed(self.pixel_seed, self.dimension);
+        let value = match PRIMES.get(self.dimension as usize) {
+            Some(&base) => scrambled_radical_inverse(base, self.index as u64, hash),
+            None => to_unit(constants::mix_seed(hash, self.index as u64)),
+        };
+        self.dimension += 1;
+        value
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+}
+
+// Radical inverse of `a` in `base`, with each digit permuted depending on the
+// digits before it (Owen scrambling). Digits past the end of `a` are scrambled
+// too, enough of them for 32 bits of precision like the Sobol points.
+fn scrambled_radical_inverse(base: u32, mut a: u64, hash: u64) -> f64 {
+    let base = base as u64;
+    let mut reversed = 0;
+    // base^(digits so far)
+    let mut scale = 1;
+    while scale < 1 << 32 {
+        let digit = (a % base) as u32;
+        // Adding the scale marks how many digits there are, so 0 and 00 are
+        // different prefixes.
+        let digit = permute(digit, base as u32, constants::mix_seed(hash, scale + reversed) as u32);
+        reversed = reversed * base + digit as u64;
+        scale *= base;
+        a /= base;
+    }
+    (reversed as f64 / scale as f64).min(ONE_MINUS_EPSILON)
+}
+
+// Owen scrambled Sobol points. Only the first two Sobol dimensions are used: each
+// 1D or 2D draw takes them with the sample index shuffled by its own hash, so all
+// dimensions are well stratified and independent of each other (Burley, "Practical
+// Hash-based Owen Scrambling", 2020).
+pub struct SobolSampler {
+    seed: u64,
+    pixel_seed: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> SobolSampler {
+        SobolSampler {
+            seed,
+            pixel_seed: 0,
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel_seed = pixel_seed(self.seed, x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        let hash = constants::mix_seed(self.pixel_seed, self.dimension);
+        self.dimension += 1;
+        scrambled_sobol(self.index, hash).0
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let hash = constants::mix_seed(self.pixel_seed, self.dimension);
+        self.dimension += 2;
+        scrambled_sobol(self.index, hash)
+    }
+}
+
+// Point `index` of the 2D Sobol sequence, shuffled and scrambled by `hash`.
+fn scrambled_sobol(index: u32, hash: u64) -> (f64, f64) {
+    let i = nested_uniform_scramble(index, constants::mix_seed(hash, 1) as u32);
+    sobol_point(i, hash)
+}
+
+// Point `index` of the 2D Sobol sequence, Owen scrambled by `hash`.
+fn sobol_point(index: u32, hash: u64) -> (f64, f64) {
+    (
+        sobol_unit(nested_uniform_scramble(index.reverse_bits(), hash as u32)),
+        sobol_unit(nested_uniform_scramble(sobol_second(index), (hash >> 32) as u32)),
+    )
+}
+
+// The second Sobol dimension; the first is the index with its bits reversed.
+fn sobol_second(mut index: u32) -> u32 {
+    let mut v = 1 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+// Owen scrambling of a 32 bit fixed point number: every bit is flipped or not
+// depending on the bits above it. Uses the Laine-Karras hash, which does the same
+// from the bottom bit up, on the reversed bits.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits().wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+fn sobol_unit(bits: u32) -> f64 {
+    bits as f64 / 4_294_967_296.0
+}
+
+// One Owen scrambled Sobol sequence for the whole image, handed out to the pixels
+// in blocks along a Z shaped (Morton) curve. Each pixel still gets well spread
+// samples, and neighbouring pixels get blocks that complement each other, so the
+// error they make differs in a way that looks like blue noise: fine grained and
+// much less visible at low sample counts than white noise. The quadtree of the Z
+// curve is shuffled at every level and for every dimension, which hides its
+// structure (Ahmed and Wonka, "Screen-Space Blue-Noise Diffusion of Monte Carlo
+// Sampling Error via Hierarchical Ordering of Pixels", 2020).
+pub struct BlueNoiseSampler {
+    seed: u64,
+    // Samples per pixel, rounded up to a power of two.
+    log2_samples: u32,
+    morton_index: u64,
+    dimension: u64,
+}
+
+// Pixel coordinates past this many bits wrap around.
+const MORTON_BITS: u32 = 16;
+
+impl BlueNoiseSampler {
+    pub fn new(seed: u64, samples: u32) -> BlueNoiseSampler {
+        BlueNoiseSampler {
+            seed,
+            log2_samples: samples.max(1).next_power_of_two().trailing_zeros(),
+            morton_index: 0,
+            dimension: 0,
+        }
+    }
+
+    // Index into the Sobol sequence for the current sample and dimension. The Z
+    // curve index is read as base 4 digits, one per quadtree level, and each
+    // digit goes through one of the 24 orders of four, picked by a hash of the
+    // digits above it.
+    fn sample_index(&self) -> u32 {
+        const ORDERS: [[u64; 4]; 24] = [
+            [0, 1, 2, 3], [0, 1, 3, 2], [0, 2, 1, 3], [0, 2, 3, 1], [0, 3, 2, 1], [0, 3, 1, 2],
+            [1, 0, 2, 3], [1, 0, 3, 2], [1, 2, 0, 3], [1, 2, 3, 0], [1, 3, 2, 0], [1, 3, 0, 2],
+            [2, 1, 0, 3], [2, 1, 3, 0], [2, 0, 1, 3], [2, 0, 3, 1], [2, 3, 0, 1], [2, 3, 1, 0],
+            [3, 1, 2, 0], [3, 1, 0, 2], [3, 2, 1, 0], [3, 2, 0, 1], [3, 0, 2, 1], [3, 0, 1, 2],
+        ];
+        let hash = constants::mix_seed(self.seed, self.dimension);
+
+        // With an odd power of two samples per pixel the lowest digit is a single
+        // bit, flipped or not.
+        let odd = self.log2_samples & 1;
+        let digits = MORTON_BITS + self.log2_samples.div_ceil(2);
+        let mut index = 0;
+        for i in (odd..digits).rev() {
+            let shift = 2 * i - odd;
+            let digit = (self.morton_index >> shift) & 3;
+            let higher = self.morton_index >> (shift + 2);
+            let order = constants::mix_seed(hash, higher) % 24;
+            index |= ORDERS[order as usize][digit as usize] << shift;
+        }
+        if odd == 1 {
+            let flip = constants::mix_seed(hash, self.morton_index >> 1) & 1;
+            index |= (self.morton_index & 1) ^ flip;
+        }
+        // Beyond 2^32 samples in an image the Sobol points repeat.
+        index as u32
+    }
+
+    fn next_point(&mut self, dimensions: u64) -> (f64, f64) {
+        let index = self.sample_index();
+        // Scrambled with a hash unrelated to the one ordering the digits.
+        let point = sobol_point(index, constants::mix_seed(!self.seed, self.dimension));
+        self.dimension += dimensions;
+        point
+    }
+}
+
+impl Sampler for BlueNoiseSampler {
+    fn start_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.morton_index = morton(x, y) << self.log2_samples | index as u64;
+        self.dimension = 0;
+    }
+
+    fn next_1d(&mut self) -> f64 {
+        self.next_point(1).0
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        self.next_point(2)
+    }
+}
+
+// Interleaves the low bits of x and y, x in the even bits.
+fn morton(x: u32, y: u32) -> u64 {
+    let spread = |v: u32| {
+        let mut v = (v & ((1 << MORTON_BITS) - 1)) as u64;
+        v = (v | v << 8) & 0x00ff_00ff;
+        v = (v | v << 4) & 0x0f0f_0f0f;
+        v = (v | v << 2) & 0x3333_3333;
+        (v | v << 1) & 0x5555_5555
+    };
+    spread(x) | spread(y) << 1
+}
+
+fn to_unit(bits: u64) -> f64 {
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// Element `i` of a random permutation of 0..n picked by `seed`, without building
+// the permutation (Kensler, "Correlated Multi-Jittered Sampling", 2013).
+fn permute(mut i: u32, n: u32, seed: u32) -> u32 {
+    if n <= 1 {
+        return 0;
+    }
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    // A bijection on 0..=w, repeated until it lands inside 0..n.
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break;
+        }
+    }
+    i.wrapping_add(seed) % n
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::environment::{Environment, Gradient};
+use crate::gravity::GravitySource;
+use crate::hittable_list::HittableList;
+use crate::material::{Lambertian, Metal};
+use crate::sphere::Sphere;
+use crate::vec3::{Point3, Vec3};
+
+// Everything the renderer needs to know about what is being rendered.
+pub struct Scene {
+    pub world: HittableList,
+    // Emitting objects from `world` that light sampling aims at.
+    pub lights: HittableList,
+    pub camera: Camera,
+    pub environment: Arc<dyn Environment>,
+    // Masses bending the rays; empty means rays travel in straight lines.
+    pub gravity: Vec<GravitySource>,
+}
+
+impl Scene {
+    // The built-in scene rendered when no scene file is given.
+    pub fn demo(aspect_ratio: f64) -> Scene {
+        let mut world = HittableList::new();
+
+        let ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+        let sphere1 = Arc::new(Metal::new(Color::new(0.8, 0.8, 0.0), 1.0));
+        let left_cube = Arc::new(Metal::new(Color::new(1.0, 0.4, 0.8), 0.0));
+        let right_cube = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, -0.5, -1.0),
+            0.1,
+            sphere1,
+        )));
+
+        world.add(Arc::new(Cube::new(
+            Point3::new(-2.0, -1.5, 2.0),
+            Point3::new(-1.0, 1.0, -3.0),
+            left_cube,
+        )));
+        world.add(Arc::new(Cube::new(
+            Point3::new(0.5, -0.75, -2.5),
+            Point3::new(1.5, 0.25, -1.5),
+            right_cube,
+        )));
+
+        world.add(Arc::new(Cube::new(
+            Point3::new(-5.0, -1.75, -5.5),
+            Point3::new(5.0, -1.5, 1.5),
+            ground,
+        )));
+
+        Scene {
+            world: world.into_bvh(),
+            lights: HittableList::new(),
+            camera: Camera::look_at(
+                Point3::new(0.0, 0.0, 2.0),
+                Point3::new(0.0, 0.0, 1.0),
+                Vec3::new(0.0, 1.0, 0.0),
+                90.0,
+                aspect_ratio,
+            ),
+            environment: Arc::new(Gradient::default()),
+            // Exaggerated mass for visual effect.
+            gravity: vec![GravitySource::new(Point3::new(0.0, -0.5, -1.0), 3.5e9)],
+        }
+    }
+}
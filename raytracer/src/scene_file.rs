@@ -0,0 +1,852 @@
+// Loader for TOML scene descriptions.
+//
+//     [camera]
+//     look_from = [0.0, 0.0, 2.0]
+//     look_at = [0.0, 0.0, 1.0]
+//     vfov = 90.0
+//     aperture_radius = 0.05
+//     focus_distance = 3.0
+//
+//     [materials.chrome]
+//     type = "metal"
+//     albedo = [0.8, 0.8, 0.8]
+//     fuzz = 0.1
+//
+//     [materials.floor]
+//     type = "lambertian"  # colors can also be textures
+//     albedo = { type = "checker", scale = 0.5, even = [0.2, 0.3, 0.1], odd = [0.9, 0.9, 0.9] }
+//
+//     [materials.stone]
+//     type = "metal"
+//     albedo = { type = "marble", scale = 4.0, color = [0.9, 0.9, 0.8] }  # or "perlin", "turbulence"
+//     fuzz = 0.3
+//
+//     [materials.poster]
+//     type = "diffuse_light"
+//     emit = { type = "image", file = "poster.png", wrap_u = "clamp" }  # .png, .ppm, .hdr or .pfm
+//
+//     [[objects]]
+//     type = "sphere"
+//     center = [0.0, -0.5, -1.0]
+//     radius = 0.1
+//     material = "chrome"
+//
+//     [[objects]]
+//     type = "mesh"
+//     file = "bunny.obj"   # relative to the scene file
+//     material = "chrome"  # for faces without a `usemtl` the scene knows
+//     transform = [        # optional on any object, steps apply in order
+//         { scale = [0.5, 0.5, 0.5] },
+//         { rotate = { axis = [0.0, 1.0, 0.0], degrees = 30.0 } },
+//         { translate = [1.0, 0.0, -2.0] },
+//         { matrix = [[1.0, 0.2, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]] },
+//     ]                    # the same mesh file used again is shared, not reloaded
+//
+// Spheres, cubes and triangles made of a `diffuse_light` are aimed at directly
+// by light sampling, transformed or not. Other glowing objects are only
+// found by rays that bounce into them, which is much noisier.
+//
+//     [[objects]]
+//     type = "accretion_disk"
+//     gravity = 0          # index into the `gravity` list
+//     normal = [0.0, 1.0, 0.0]
+//     inner_radius = 1.5
+//     outer_radius = 6.0
+//     temperature = 8000.0  # peak, in kelvin
+//     brightness = 2.0
+//
+//     [environment]
+//     type = "gradient"
+//     bottom = [0.81, 0.93, 0.96]
+//     top = [0.28, 0.35, 0.50]
+//
+//     [environment]        # or a panorama, .hdr, .pfm or .ppm
+//     type = "equirectangular"
+//     file = "sky.hdr"     # relative to the scene file
+//     rotation = 90.0      # degrees around the vertical axis
+//     intensity = 1.5
+//
+//     [environment]        # or a skybox, as one cross layout image
+//     type = "cubemap"
+//     file = "sky_cross.hdr"
+//
+//     [environment]        # or as six images
+//     type = "cubemap"
+//     faces = { right = "px.hdr", left = "nx.hdr", top = "py.hdr", bottom = "ny.hdr", front = "nz.hdr", back = "pz.hdr" }
+//
+//     [[gravity]]          # any number of masses, their pulls add up
+//     position = [0.0, -0.5, -1.0]
+//     mass = 3.5e9
+//     model = "kerr"       # "newtonian" (default), "schwarzschild" or "kerr"
+//     spin = 0.9           # kerr only, a / M in [0, 1)
+//     axis = [0.0, 1.0, 0.0]
+//
+// See `scenes/demo.toml` for a complete example.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::accretion_disk::AccretionDisk;
+use crate::camera::{Camera, Lens};
+use crate::cube::Cube;
+use crate::environment::{CubeMap, Environment, EquirectangularMap, Gradient};
+use crate::gravity::GravitySource;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::image::{ImageBuffer, Wrap};
+use crate::image_io;
+use crate::obj;
+use crate::scene::Scene;
+use crate::sphere::Sphere;
+use crate::texture::{Checker, ImageTexture, NoisePattern, NoiseTexture, SolidColor, Texture};
+use crate::transform::{self, Transform};
+use crate::triangle::Triangle;
+use crate::vec3::{self, Vec3};
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(PathBuf, io::Error),
+    // Syntax errors and missing, unknown or mistyped keys, as reported by the TOML parser.
+    Parse(toml::de::Error),
+    // The file is well formed but does not describe a valid scene.
+    Invalid { line: usize, key: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            SceneError::Parse(e) => write!(f, "{}", e),
+            SceneError::Invalid { line, key, message } => {
+                write!(f, "line {}, `{}`: {}", line, key, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// `aspect_ratio` is used for the camera unless the file sets its own.
+pub fn load_scene(path: &Path, aspect_ratio: f64) -> Result<Scene, SceneError> {
+    let src = fs::read_to_string(path).map_err(|e| SceneError::Io(path.to_path_buf(), e))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    parse_scene(&src, base_dir, aspect_ratio)
+}
+
+// Files the scene refers to, such as meshes, are looked up relative to `base_dir`.
+pub fn parse_scene(src: &str, base_dir: &Path, aspect_ratio: f64) -> Result<Scene, SceneError> {
+    let file: SceneFile = toml::from_str(src).map_err(SceneError::Parse)?;
+    Builder {
+        src,
+        base_dir,
+        aspect_ratio,
+    }
+    .build(file)
+}
+
+type Triple = [f64; 3];
+
+fn vec3(v: Triple) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    camera: Option<Spanned<CameraDesc>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<MaterialDesc>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectEntry>>,
+    environment: Option<Spanned<EnvironmentDesc>>,
+    #[serde(default)]
+    gravity: Vec<Spanned<GravityDesc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDesc {
+    #[serde(default = "default_look_from")]
+    look_from: Triple,
+    #[serde(default = "default_look_at")]
+    look_at: Triple,
+    #[serde(default = "default_vup")]
+    vup: Triple,
+    #[serde(default = "default_vfov")]
+    vfov: f64,
+    // Defaults to the aspect ratio of the rendered image.
+    aspect_ratio: Option<f64>,
+    #[serde(default)]
+    aperture_radius: f64,
+    // Defaults to the distance between `look_from` and `look_at`.
+    focus_distance: Option<f64>,
+    // 0 for a round aperture, otherwise the number of polygon sides.
+    #[serde(default)]
+    aperture_blades: u32,
+    #[serde(default)]
+    aperture_rotation: f64,
+}
+
+fn default_look_from() -> Triple {
+    [0.0, 0.0, 2.0]
+}
+
+fn default_look_at() -> Triple {
+    [0.0, 0.0, 1.0]
+}
+
+fn default_vup() -> Triple {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_vfov() -> f64 {
+    90.0
+}
+
+impl Default for CameraDesc {
+    fn default() -> CameraDesc {
+        CameraDesc {
+            look_from: default_look_from(),
+            look_at: default_look_at(),
+            vup: default_vup(),
+            vfov: default_vfov(),
+            aspect_ratio: None,
+            aperture_radius: 0.0,
+            focus_distance: None,
+            aperture_blades: 0,
+            aperture_rotation: 0.0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum MaterialDesc {
+    Lambertian { albedo: ColorDesc },
+    Metal { albedo: ColorDesc, fuzz: f64 },
+    Dielectric { ior: f64 },
+    DiffuseLight { emit: ColorDesc },
+}
+
+// A plain color, or a texture table.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "an [r, g, b] color or a texture table")]
+enum ColorDesc {
+    Constant(Triple),
+    Texture(Box<TextureDesc>),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum TextureDesc {
+    Checker {
+        // Width of the cubes.
+        scale: f64,
+        even: ColorDesc,
+        odd: ColorDesc,
+    },
+    Image {
+        file: String,
+        #[serde(default)]
+        wrap_u: WrapDesc,
+        #[serde(default)]
+        wrap_v: WrapDesc,
+    },
+    Perlin {
+        #[serde(flatten)]
+        noise: NoiseDesc,
+    },
+    Turbulence {
+        #[serde(flatten)]
+        noise: NoiseDesc,
+    },
+    Marble {
+        #[serde(flatten)]
+        noise: NoiseDesc,
+    },
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum WrapDesc {
+    #[default]
+    Repeat,
+    Clamp,
+}
+
+#[derive(Deserialize)]
+struct NoiseDesc {
+    #[serde(default = "default_noise_scale")]
+    scale: f64,
+    #[serde(default = "default_noise_color")]
+    color: Triple,
+    #[serde(default)]
+    seed: u64,
+}
+
+fn default_noise_scale() -> f64 {
+    1.0
+}
+
+fn default_noise_color() -> Triple {
+    [1.0, 1.0, 1.0]
+}
+
+// An object, moved into place by the steps of `transform` in the order given.
+#[derive(Deserialize)]
+struct ObjectEntry {
+    #[serde(flatten)]
+    shape: ObjectDesc,
+    #[serde(default)]
+    transform: Vec<TransformDesc>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+enum TransformDesc {
+    Translate(Triple),
+    // Degrees, counterclockwise looking down at the tip of the axis.
+    Rotate { axis: Triple, degrees: f64 },
+    Scale(Triple),
+    // Row-major affine matrix, the last row must be [0, 0, 0, 1].
+    Matrix([[f64; 4]; 4]),
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum ObjectDesc {
+    Sphere {
+        center: Triple,
+        radius: f64,
+        material: String,
+    },
+    Cube {
+        min: Triple,
+        max: Triple,
+        material: String,
+    },
+    Triangle {
+        vertices: [Triple; 3],
+        material: String,
+    },
+    Mesh {
+        file: String,
+        material: String,
+    },
+    #[serde(rename = "accretion_disk")]
+    AccretionDisk {
+        // Index into the `gravity` list.
+        gravity: usize,
+        #[serde(default = "default_vup")]
+        normal: Triple,
+        inner_radius: f64,
+        outer_radius: f64,
+        temperature: f64,
+        #[serde(default = "default_brightness")]
+        brightness: f64,
+    },
+}
+
+fn default_brightness() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum EnvironmentDesc {
+    Gradient {
+        bottom: Triple,
+        top: Triple,
+    },
+    Equirectangular {
+        file: String,
+        // Degrees around the vertical axis.
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+    Cubemap {
+        // Either one image with the unfolded cube, or six separate ones.
+        file: Option<String>,
+        faces: Option<CubeFacesDesc>,
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CubeFacesDesc {
+    right: String,
+    left: String,
+    top: String,
+    bottom: String,
+    front: String,
+    back: String,
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GravityDesc {
+    position: Triple,
+    mass: f64,
+    #[serde(default)]
+    model: GravityModelDesc,
+    // Kerr only.
+    #[serde(default)]
+    spin: f64,
+    #[serde(default = "default_vup")]
+    axis: Triple,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum GravityModelDesc {
+    #[default]
+    Newtonian,
+    Schwarzschild,
+    Kerr,
+}
+
+struct Builder<'a> {
+    src: &'a str,
+    base_dir: &'a Path,
+    aspect_ratio: f64,
+}
+
+impl Builder<'_> {
+    fn build(&self, file: SceneFile) -> Result<Scene, SceneError> {
+        let mut materials: BTreeMap<String, Arc<dyn Material>> = BTreeMap::new();
+        for (name, desc) in &file.materials {
+            let key = format!("materials.{}", name);
+            materials.insert(name.clone(), self.material(&key, desc)?);
+        }
+
+        let mut gravity = Vec::new();
+        for (index, desc) in file.gravity.iter().enumerate() {
+            let g = desc.get_ref();
+            if !non_negative(g.mass) {
+                let key = format!("gravity[{}]", index);
+                return Err(self.invalid(desc, &key, "mass must not be negative"));
+            }
+            if !(0.0..1.0).contains(&g.spin) {
+                let key = format!("gravity[{}].spin", index);
+                return Err(self.invalid(desc, &key, "must be at least 0 and less than 1"));
+            }
+            if vec3(g.axis).near_zero() {
+                let key = format!("gravity[{}].axis", index);
+                return Err(self.invalid(desc, &key, "must not be zero"));
+            }
+            gravity.push(match g.model {
+                GravityModelDesc::Newtonian => GravitySource::new(vec3(g.position), g.mass),
+                GravityModelDesc::Schwarzschild => GravitySource::black_hole(vec3(g.position), g.mass),
+                GravityModelDesc::Kerr => GravitySource::kerr(vec3(g.position), g.mass, g.spin, vec3(g.axis)),
+            });
+        }
+
+        let mut world = HittableList::new();
+        // Meshes used more than once are loaded once and shared.
+        let mut meshes: BTreeMap<(&str, &str), Arc<dyn Hittable>> = BTreeMap::new();
+        // Glowing shapes that know how to sample themselves.
+        let mut lights = HittableList::new();
+        for (index, desc) in file.objects.iter().enumerate() {
+            let key = format!("objects[{}]", index);
+            let entry = desc.get_ref();
+            let mut light = false;
+            let object: Arc<dyn Hittable> = match &entry.shape {
+                ObjectDesc::Sphere {
+                    center,
+                    radius,
+                    material,
+                } => {
+                    if !positive(*radius) {
+                        return Err(self.invalid(desc, &key, "sphere radius must be positive"));
+                    }
+                    let mat = self.lookup(&materials, desc, &key, material)?;
+                    light = mat.is_emissive();
+                    Arc::new(Sphere::new(vec3(*center), *radius, mat))
+                }
+                ObjectDesc::Cube { min, max, material } => {
+                    let mat = self.lookup(&materials, desc, &key, material)?;
+                    light = mat.is_emissive();
+                    Arc::new(Cube::new(vec3(*min), vec3(*max), mat))
+                }
+                ObjectDesc::Triangle { vertices, material } => {
+                    let mat = self.lookup(&materials, desc, &key, material)?;
+                    light = mat.is_emissive();
+                    let [p0, p1, p2] = vertices.map(vec3);
+                    if vec3::cross(p1 - p0, p2 - p0).near_zero() {
+                        return Err(self.invalid(desc, &key, "triangle has no area"));
+                    }
+                    Arc::new(Triangle::new(p0, p1, p2, mat))
+                }
+                ObjectDesc::Mesh { file, material } => match meshes.get(&(file.as_str(), material.as_str())) {
+                    Some(mesh) => mesh.clone(),
+                    None => {
+                        let mat = self.lookup(&materials, desc, &key, material)?;
+                        let mesh: Arc<dyn Hittable> = Arc::new(
+                            obj::load_obj(&self.base_dir.join(file), mat, &materials)
+                                .map_err(|e| self.invalid(desc, &format!("{}.file", key), &e.to_string()))?,
+                        );
+                        meshes.insert((file, material), mesh.clone());
+                        mesh
+                    }
+                },
+                ObjectDesc::AccretionDisk {
+                    gravity: source,
+                    normal,
+                    inner_radius,
+                    outer_radius,
+                    temperature,
+                    brightness,
+                } => {
+                    // The glow is worked out from the disk's place in the scene.
+                    if !entry.transform.is_empty() {
+                        let message = "accretion disks cannot be transformed, set `normal` instead";
+                        return Err(self.invalid(desc, &format!("{}.transform", key), message));
+                    }
+                    let source = gravity.get(*source).ok_or_else(|| {
+                        let message = format!("there are only {} gravity sources", gravity.len());
+                        self.invalid(desc, &format!("{}.gravity", key), &message)
+                    })?;
+                    // Circular orbits need r > 1.5 r_s, the photon sphere.
+                    if *inner_radius <= 1.5 * source.schwarzschild_radius() {
+                        let message = format!(
+                            "must be outside the photon sphere ({})",
+                            1.5 * source.schwarzschild_radius()
+                        );
+                        return Err(self.invalid(desc, &format!("{}.inner_radius", key), &message));
+                    }
+                    if outer_radius <= inner_radius {
+                        return Err(self.invalid(desc, &key, "outer_radius must be larger than inner_radius"));
+                    }
+                    if !positive(*temperature) || !non_negative(*brightness) {
+                        return Err(self.invalid(desc, &key, "temperature must be positive and brightness not negative"));
+                    }
+                    if vec3(*normal).near_zero() {
+                        return Err(self.invalid(desc, &format!("{}.normal", key), "must not be zero"));
+                    }
+                    Arc::new(AccretionDisk::new(
+                        source,
+                        vec3(*normal),
+                        *inner_radius,
+                        *outer_radius,
+                        *temperature,
+                        *brightness,
+                    ))
+                }
+            };
+            let object = self.transform(desc, &key, object, &entry.transform)?;
+            if light {
+                lights.add(object.clone());
+            }
+            world.add(object);
+        }
+
+        let camera = self.camera(file.camera.as_ref())?;
+
+        let environment = match &file.environment {
+            Some(desc) => self.environment(desc)?,
+            None => Arc::new(Gradient::default()),
+        };
+
+        Ok(Scene {
+            world: world.into_bvh(),
+            lights,
+            camera,
+            environment,
+            gravity,
+        })
+    }
+
+    fn environment(&self, desc: &Spanned<EnvironmentDesc>) -> Result<Arc<dyn Environment>, SceneError> {
+        match desc.get_ref() {
+            EnvironmentDesc::Gradient { bottom, top } => Ok(Arc::new(Gradient::new(vec3(*bottom), vec3(*top)))),
+            EnvironmentDesc::Equirectangular {
+                file,
+                rotation,
+                intensity,
+            } => {
+                if !non_negative(*intensity) {
+                    return Err(self.invalid(desc, "environment.intensity", "must not be negative"));
+                }
+                let image = self.environment_image(desc, "environment.file", file)?;
+                Ok(Arc::new(EquirectangularMap::new(image, *rotation, *intensity)))
+            }
+            EnvironmentDesc::Cubemap {
+                file,
+                faces,
+                rotation,
+                intensity,
+            } => {
+                if !non_negative(*intensity) {
+                    return Err(self.invalid(desc, "environment.intensity", "must not be negative"));
+                }
+                match (file, faces) {
+                    (Some(file), None) => {
+                        let image = self.environment_image(desc, "environment.file", file)?;
+                        let cube = CubeMap::from_cross(&image, *rotation, *intensity).ok_or_else(|| {
+                            self.invalid(
+                                desc,
+                                "environment.file",
+                                "must be a 4:3 or 3:4 cross of square faces",
+                            )
+                        })?;
+                        Ok(Arc::new(cube))
+                    }
+                    (None, Some(f)) => {
+                        let faces = [
+                            self.environment_image(desc, "environment.faces.right", &f.right)?,
+                            self.environment_image(desc, "environment.faces.left", &f.left)?,
+                            self.environment_image(desc, "environment.faces.top", &f.top)?,
+                            self.environment_image(desc, "environment.faces.bottom", &f.bottom)?,
+                            self.environment_image(desc, "environment.faces.front", &f.front)?,
+                            self.environment_image(desc, "environment.faces.back", &f.back)?,
+                        ];
+                        Ok(Arc::new(CubeMap::new(faces, *rotation, *intensity)))
+                    }
+                    _ => Err(self.invalid(desc, "environment", "needs exactly one of `file` and `faces`")),
+                }
+            }
+        }
+    }
+
+    // Images are looked up relative to the scene file.
+    fn environment_image(
+        &self,
+        desc: &Spanned<EnvironmentDesc>,
+        key: &str,
+        file: &str,
+    ) -> Result<ImageBuffer, SceneError> {
+        let path = self.base_dir.join(file);
+        image_io::read_image(&path).map_err(|e| {
+            let message = format!("cannot load {}: {}", path.display(), e);
+            self.invalid(desc, key, &message)
+        })
+    }
+
+    fn camera(&self, desc: Option<&Spanned<CameraDesc>>) -> Result<Camera, SceneError> {
+        let default = CameraDesc::default();
+        let (c, line) = match desc {
+            Some(desc) => (desc.get_ref(), self.line_of(desc.span().start)),
+            None => (&default, 1),
+        };
+        let look_from = vec3(c.look_from);
+        let look_at = vec3(c.look_at);
+        let vup = vec3(c.vup);
+
+        if (look_from - look_at).near_zero() {
+            return Err(invalid_at(line, "camera.look_at", "must differ from `look_from`"));
+        }
+        if vec3::cross(vup, look_from - look_at).near_zero() {
+            return Err(invalid_at(line, "camera.vup", "must not be parallel to the view direction"));
+        }
+        if !(c.vfov > 0.0 && c.vfov < 180.0) {
+            return Err(invalid_at(line, "camera.vfov", "must be between 0 and 180 degrees"));
+        }
+        let aspect_ratio = c.aspect_ratio.unwrap_or(self.aspect_ratio);
+        if !positive(aspect_ratio) {
+            return Err(invalid_at(line, "camera.aspect_ratio", "must be positive"));
+        }
+
+        if !non_negative(c.aperture_radius) {
+            return Err(invalid_at(line, "camera.aperture_radius", "must not be negative"));
+        }
+        let focus_dist = c.focus_distance.unwrap_or((look_from - look_at).length());
+        if !positive(focus_dist) {
+            return Err(invalid_at(line, "camera.focus_distance", "must be positive"));
+        }
+        if c.aperture_blades == 1 || c.aperture_blades == 2 {
+            return Err(invalid_at(line, "camera.aperture_blades", "must be 0 (round) or at least 3"));
+        }
+        let lens = Lens {
+            aperture_radius: c.aperture_radius,
+            focus_dist,
+            blades: c.aperture_blades,
+            rotation: c.aperture_rotation,
+        };
+
+        Ok(Camera::with_lens(look_from, look_at, vup, c.vfov, aspect_ratio, lens))
+    }
+
+    fn material(&self, key: &str, desc: &Spanned<MaterialDesc>) -> Result<Arc<dyn Material>, SceneError> {
+        Ok(match desc.get_ref() {
+            MaterialDesc::Lambertian { albedo } => {
+                let albedo = self.texture(desc, &format!("{}.albedo", key), albedo)?;
+                Arc::new(Lambertian::textured(albedo))
+            }
+            MaterialDesc::Metal { albedo, fuzz } => {
+                if !non_negative(*fuzz) {
+                    return Err(self.invalid(desc, key, "metal fuzz must not be negative"));
+                }
+                let albedo = self.texture(desc, &format!("{}.albedo", key), albedo)?;
+                Arc::new(Metal::textured(albedo, *fuzz))
+            }
+            MaterialDesc::Dielectric { ior } => {
+                if !positive(*ior) {
+                    return Err(self.invalid(desc, key, "dielectric ior must be positive"));
+                }
+                Arc::new(Dielectric::new(*ior))
+            }
+            MaterialDesc::DiffuseLight { emit } => {
+                let emit = self.texture(desc, &format!("{}.emit", key), emit)?;
+                Arc::new(DiffuseLight::textured(emit))
+            }
+        })
+    }
+
+    fn texture(
+        &self,
+        at: &Spanned<MaterialDesc>,
+        key: &str,
+        desc: &ColorDesc,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        let texture = match desc {
+            ColorDesc::Constant(color) => return Ok(Arc::new(SolidColor::new(vec3(*color)))),
+            ColorDesc::Texture(texture) => texture.as_ref(),
+        };
+        Ok(match texture {
+            TextureDesc::Checker { scale, even, odd } => {
+                if !positive(*scale) {
+                    return Err(self.invalid(at, &format!("{}.scale", key), "must be positive"));
+                }
+                let even = self.texture(at, &format!("{}.even", key), even)?;
+                let odd = self.texture(at, &format!("{}.odd", key), odd)?;
+                Arc::new(Checker::new(*scale, even, odd))
+            }
+            TextureDesc::Image { file, wrap_u, wrap_v } => {
+                let path = self.base_dir.join(file);
+                let image = image_io::read_image(&path).map_err(|e| {
+                    let message = format!("cannot load {}: {}", path.display(), e);
+                    self.invalid(at, &format!("{}.file", key), &message)
+                })?;
+                Arc::new(ImageTexture::new(image, wrap(*wrap_u), wrap(*wrap_v)))
+            }
+            TextureDesc::Perlin { noise } => self.noise(at, key, NoisePattern::Perlin, noise)?,
+            TextureDesc::Turbulence { noise } => self.noise(at, key, NoisePattern::Turbulence, noise)?,
+            TextureDesc::Marble { noise } => self.noise(at, key, NoisePattern::Marble, noise)?,
+        })
+    }
+
+    fn noise(
+        &self,
+        at: &Spanned<MaterialDesc>,
+        key: &str,
+        pattern: NoisePattern,
+        desc: &NoiseDesc,
+    ) -> Result<Arc<dyn Texture>, SceneError> {
+        if !positive(desc.scale) {
+            return Err(self.invalid(at, &format!("{}.scale", key), "must be positive"));
+        }
+        Ok(Arc::new(NoiseTexture::new(pattern, desc.scale, vec3(desc.color), desc.seed)))
+    }
+
+    fn transform(
+        &self,
+        at: &Spanned<ObjectEntry>,
+        key: &str,
+        object: Arc<dyn Hittable>,
+        steps: &[TransformDesc],
+    ) -> Result<Arc<dyn Hittable>, SceneError> {
+        if steps.is_empty() {
+            return Ok(object);
+        }
+
+        let mut matrix = Matrix4::identity();
+        for (index, step) in steps.iter().enumerate() {
+            let step_key = format!("{}.transform[{}]", key, index);
+            let m = match step {
+                TransformDesc::Translate(offset) => transform::translation(vec3(*offset)),
+                TransformDesc::Rotate { axis, degrees } => {
+                    if vec3(*axis).near_zero() {
+                        return Err(self.invalid(at, &step_key, "rotation axis must not be zero"));
+                    }
+                    transform::rotation(vec3(*axis), *degrees)
+                }
+                TransformDesc::Scale(factors) => {
+                    if factors.contains(&0.0) {
+                        return Err(self.invalid(at, &step_key, "scale factors must not be zero"));
+                    }
+                    transform::scaling(vec3(*factors))
+                }
+                TransformDesc::Matrix(rows) => {
+                    if rows[3] != [0.0, 0.0, 0.0, 1.0] {
+                        return Err(self.invalid(at, &step_key, "last matrix row must be [0, 0, 0, 1]"));
+                    }
+                    // cgmath takes columns.
+                    let c = |j: usize| Vector4::new(rows[0][j], rows[1][j], rows[2][j], rows[3][j]);
+                    Matrix4::from_cols(c(0), c(1), c(2), c(3))
+                }
+            };
+            matrix = m * matrix;
+        }
+        if matrix.determinant().abs() < 1e-12 {
+            return Err(self.invalid(at, &format!("{}.transform", key), "flattens the object, it cannot be undone"));
+        }
+        Ok(Arc::new(Transform::new(object, matrix)))
+    }
+
+    fn lookup<T>(
+        &self,
+        materials: &BTreeMap<String, Arc<dyn Material>>,
+        at: &Spanned<T>,
+        key: &str,
+        name: &str,
+    ) -> Result<Arc<dyn Material>, SceneError> {
+        materials.get(name).cloned().ok_or_else(|| {
+            self.invalid(
+                at,
+                &format!("{}.material", key),
+                &format!("unknown material `{}`", name),
+            )
+        })
+    }
+
+    fn invalid<T>(&self, at: &Spanned<T>, key: &str, message: &str) -> SceneError {
+        invalid_at(self.line_of(at.span().start), key, message)
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.src[..offset].matches('\n').count() + 1
+    }
+}
+
+fn wrap(desc: WrapDesc) -> Wrap {
+    match desc {
+        WrapDesc::Repeat => Wrap::Repeat,
+        WrapDesc::Clamp => Wrap::Clamp,
+    }
+}
+
+fn invalid_at(line: usize, key: &str, message: &str) -> SceneError {
+    SceneError::Invalid {
+        line,
+        key: key.to_string(),
+        message: message.to_string(),
+    }
+}
+
+// Both are false for NaN, so a NaN in the scene file is rejected too.
+fn positive(x: f64) -> bool {
+    x > 0.0
+}
+
+fn non_negative(x: f64) -> bool {
+    x >= 0.0
+}
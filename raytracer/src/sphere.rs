@@ -1,18 +1,22 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
+use crate::aabb::Aabb;
+use crate::constants::{INFINITY, PI};
 use crate::hittable::{HitRecord, Hittable};
+use crate::onb::{self, Onb};
 use crate::ray::Ray;
-use crate::vec3::{self, Point3};
+use crate::sampler::Sampler;
+use crate::vec3::{self, Point3, Vec3};
 use crate::material::Material;
 
 pub struct Sphere {
     center: Point3,
     radius: f64,
-    mat: Rc<dyn Material>
+    mat: Arc<dyn Material>
 }
 
 impl Sphere {
-    pub fn new(cen: Point3, rad: f64, m: Rc<dyn Material>) -> Sphere {
+    pub fn new(cen: Point3, rad: f64, m: Arc<dyn Material>) -> Sphere {
         Sphere {
             center: cen,
             radius: rad,
@@ -22,6 +26,11 @@ impl Sphere {
 }
 
 impl Hittable for Sphere {
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
         let oc = r.origin() - self.center;
         let a = r.direction().length_squared();
@@ -46,7 +55,49 @@ impl Hittable for Sphere {
         rec.p = r.at(rec.t);
         let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = sphere_uv(outward_normal);
         rec.mat = Some(self.mat.clone());
         true
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let mut rec = HitRecord::new();
+        if !self.hit(&Ray::new(origin, direction), 0.001, INFINITY, &mut rec) {
+            return 0.0;
+        }
+
+        let dist2 = (self.center - origin).length_squared();
+        let r2 = self.radius * self.radius;
+        if dist2 > r2 {
+            // Uniform over the cone of directions the sphere covers.
+            let cos_theta_max = f64::sqrt(1.0 - r2 / dist2);
+            return onb::uniform_cone_pdf(cos_theta_max);
+        }
+
+        // From inside, uniform over the surface, turned into solid angle.
+        let to_hit = rec.p - origin;
+        let cosine = vec3::dot(rec.normal, vec3::unit_vector(to_hit)).abs();
+        to_hit.length_squared() * onb::on_unit_sphere_pdf() / (cosine * r2)
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let to_center = self.center - origin;
+        let dist2 = to_center.length_squared();
+        let r2 = self.radius * self.radius;
+        let (a, b) = sampler.next_2d();
+        if dist2 <= r2 {
+            return self.center + self.radius * onb::on_unit_sphere(a, b) - origin;
+        }
+
+        let cos_theta_max = f64::sqrt(1.0 - r2 / dist2);
+        Onb::new(to_center).local(onb::uniform_cone(a, b, cos_theta_max))
+    }
+}
+
+// Longitude and latitude of a point on the unit sphere, both in [0, 1]. u goes
+// around the y axis starting at -x, v runs from the bottom (-y) to the top.
+fn sphere_uv(p: Point3) -> (f64, f64) {
+    let theta = f64::acos((-p.y()).clamp(-1.0, 1.0));
+    let phi = f64::atan2(-p.z(), p.x()) + PI;
+    (phi / (2.0 * PI), theta / PI)
 }
@@ -0,0 +1,127 @@
+// Colors that vary over a surface, looked up with the surface coordinates (u, v)
+// of the hit and the hit point itself.
+
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::image::{ImageBuffer, Wrap};
+use crate::perlin::Perlin;
+use crate::vec3::Point3;
+
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+}
+
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> SolidColor {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        self.color
+    }
+}
+
+// Checkerboard in space, made of cubes `scale` units wide, so it wraps around any
+// shape without needing surface coordinates.
+pub struct Checker {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl Checker {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Checker {
+        Checker {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let cell = (self.inv_scale * p.x()).floor() as i64
+            + (self.inv_scale * p.y()).floor() as i64
+            + (self.inv_scale * p.z()).floor() as i64;
+        if cell.rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+// Image mapped onto the surface coordinates, with (0, 0) at the bottom left of
+// the image and (1, 1) at the top right.
+pub struct ImageTexture {
+    image: ImageBuffer,
+    wrap_u: Wrap,
+    wrap_v: Wrap,
+}
+
+impl ImageTexture {
+    pub fn new(image: ImageBuffer, wrap_u: Wrap, wrap_v: Wrap) -> ImageTexture {
+        ImageTexture { image, wrap_u, wrap_v }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        self.image.bilinear(u, 1.0 - v, self.wrap_u, self.wrap_v)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NoisePattern {
+    // Plain Perlin noise, mapped to [0, 1].
+    Perlin,
+    // Several octaves of noise added up.
+    Turbulence,
+    // Stripes along z, bent by turbulence.
+    Marble,
+}
+
+// Solid noise in space, times `color`. `scale` is the frequency: larger values
+// give finer detail.
+pub struct NoiseTexture {
+    perlin: Perlin,
+    pattern: NoisePattern,
+    scale: f64,
+    color: Color,
+}
+
+const TURBULENCE_DEPTH: u32 = 7;
+
+impl NoiseTexture {
+    pub fn new(pattern: NoisePattern, scale: f64, color: Color, seed: u64) -> NoiseTexture {
+        NoiseTexture {
+            perlin: Perlin::new(seed),
+            pattern,
+            scale,
+            color,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+        let amount = match self.pattern {
+            NoisePattern::Perlin => 0.5 * (1.0 + self.perlin.noise(self.scale * p)),
+            NoisePattern::Turbulence => self.perlin.turbulence(self.scale * p, TURBULENCE_DEPTH),
+            // The scale only sets the stripe spacing, the veins keep their size.
+            NoisePattern::Marble => {
+                let phase = self.scale * p.z() + 10.0 * self.perlin.turbulence(p, TURBULENCE_DEPTH);
+                0.5 * (1.0 + phase.sin())
+            }
+        };
+        amount * self.color
+    }
+}
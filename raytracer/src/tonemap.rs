@@ -0,0 +1,237 @@
+// Display pipeline: turns the linear, unclamped render into values for an 8 or
+// 16 bit image. Exposure, then a tone mapping operator squeezing highlights into
+// [0, 1], then the sRGB transfer function, then (optionally) dithering while
+// quantizing.
+
+use std::sync::OnceLock;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::color::Color;
+use crate::image::{ImageBuffer, LdrImage};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToneMap {
+    // Clip everything above 1.
+    Clamp,
+    // x / (1 + x)
+    Reinhard,
+    // John Hable's filmic curve from Uncharted 2.
+    Hable,
+    // Krzysztof Narkowicz's fit of the ACES filmic curve.
+    Aces,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dither {
+    None,
+    // 8x8 Bayer matrix.
+    Ordered,
+    // 64x64 void-and-cluster blue noise mask.
+    BlueNoise,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DisplayTransform {
+    // Exposure adjustment in stops: every stop doubles the brightness.
+    pub exposure: f64,
+    pub tone_map: ToneMap,
+    pub dither: Dither,
+}
+
+impl Default for DisplayTransform {
+    fn default() -> DisplayTransform {
+        DisplayTransform {
+            exposure: 0.0,
+            tone_map: ToneMap::Clamp,
+            dither: Dither::None,
+        }
+    }
+}
+
+impl DisplayTransform {
+    // Linear color to sRGB encoded display values in [0, 1].
+    pub fn apply(&self, c: Color) -> Color {
+        let scale = f64::powf(2.0, self.exposure);
+        let map = |v: f64| srgb_encode(self.tone_map.apply(v * scale).clamp(0.0, 1.0));
+        Color::new(map(c.x()), map(c.y()), map(c.z()))
+    }
+
+    pub fn to_ldr(&self, image: &ImageBuffer, max_value: u16) -> LdrImage {
+        let levels = max_value as f64;
+        let mut data = Vec::with_capacity(image.width() * image.height() * 3);
+        for y in 0..image.height() {
+            for x in 0..image.width() {
+                // Threshold in [0, 1): 0.5 is plain rounding.
+                let threshold = match self.dither {
+                    Dither::None => 0.5,
+                    Dither::Ordered => bayer_threshold(x, y),
+                    Dither::BlueNoise => blue_noise_threshold(x, y),
+                };
+                let c = self.apply(image.get(x, y));
+                for v in [c.x(), c.y(), c.z()] {
+                    data.push((v * levels + threshold).floor().min(levels) as u16);
+                }
+            }
+        }
+        LdrImage {
+            width: image.width(),
+            height: image.height(),
+            max_value,
+            data,
+        }
+    }
+}
+
+impl ToneMap {
+    pub fn apply(self, x: f64) -> f64 {
+        let x = x.max(0.0);
+        match self {
+            ToneMap::Clamp => x,
+            ToneMap::Reinhard => x / (1.0 + x),
+            ToneMap::Hable => {
+                const EXPOSURE_BIAS: f64 = 2.0;
+                const WHITE_POINT: f64 = 11.2;
+                hable_partial(x * EXPOSURE_BIAS) / hable_partial(WHITE_POINT)
+            }
+            ToneMap::Aces => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                (x * (a * x + b)) / (x * (c * x + d) + e)
+            }
+        }
+    }
+}
+
+fn hable_partial(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.50, 0.10, 0.20, 0.02, 0.30);
+    ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f
+}
+
+// Piecewise sRGB transfer function: linear near black, then a 2.4 power curve.
+pub fn srgb_encode(v: f64) -> f64 {
+    if v <= 0.0031308 {
+        12.92 * v
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Inverse of `srgb_encode`, for reading 8 and 16 bit images back as linear color.
+pub fn srgb_decode(v: f64) -> f64 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn bayer_threshold(x: usize, y: usize) -> f64 {
+    const BAYER: [[u8; 8]; 8] = [
+        [0, 32, 8, 40, 2, 34, 10, 42],
+        [48, 16, 56, 24, 50, 18, 58, 26],
+        [12, 44, 4, 36, 14, 46, 6, 38],
+        [60, 28, 52, 20, 62, 30, 54, 22],
+        [3, 35, 11, 43, 1, 33, 9, 41],
+        [51, 19, 59, 27, 49, 17, 57, 25],
+        [15, 47, 7, 39, 13, 45, 5, 37],
+        [63, 31, 55, 23, 61, 29, 53, 21],
+    ];
+    (BAYER[y % 8][x % 8] as f64 + 0.5) / 64.0
+}
+
+const BLUE_NOISE_SIZE: usize = 64;
+
+fn blue_noise_threshold(x: usize, y: usize) -> f64 {
+    static MASK: OnceLock<Vec<f64>> = OnceLock::new();
+    let mask = MASK.get_or_init(|| blue_noise_mask(BLUE_NOISE_SIZE));
+    mask[(y % BLUE_NOISE_SIZE) * BLUE_NOISE_SIZE + x % BLUE_NOISE_SIZE]
+}
+
+// Ulichney's void-and-cluster method. Every pixel gets a rank by adding points
+// one at a time where they are furthest from all others, which spreads each
+// threshold level evenly over the tile. Returns (rank + 0.5) / pixel count.
+fn blue_noise_mask(size: usize) -> Vec<f64> {
+    const SIGMA: f64 = 1.5;
+    const RADIUS: i64 = 6;
+    let n = size * size;
+
+    // Gaussian "energy" each point spreads over its neighbours (on a torus, so the
+    // mask tiles seamlessly).
+    let mut kernel = Vec::new();
+    for dy in -RADIUS..=RADIUS {
+        for dx in -RADIUS..=RADIUS {
+            let w = f64::exp(-((dx * dx + dy * dy) as f64) / (2.0 * SIGMA * SIGMA));
+            kernel.push((dx, dy, w));
+        }
+    }
+    let splat = |energy: &mut [f64], i: usize, sign: f64| {
+        let (x, y) = ((i % size) as i64, (i / size) as i64);
+        for &(dx, dy, w) in &kernel {
+            let xx = (x + dx).rem_euclid(size as i64) as usize;
+            let yy = (y + dy).rem_euclid(size as i64) as usize;
+            energy[yy * size + xx] += sign * w;
+        }
+    };
+    let tightest_cluster = |pattern: &[bool], energy: &[f64]| {
+        (0..n)
+            .filter(|&i| pattern[i])
+            .max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+    let largest_void = |pattern: &[bool], energy: &[f64]| {
+        (0..n)
+            .filter(|&i| !pattern[i])
+            .min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+            .unwrap()
+    };
+
+    // Random initial points, then move points out of clusters into voids until
+    // that no longer changes anything.
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut pattern = vec![false; n];
+    let mut energy = vec![0.0; n];
+    let initial = n / 10;
+    while pattern.iter().filter(|&&p| p).count() < initial {
+        let i = rng.random_range(0..n);
+        if !pattern[i] {
+            pattern[i] = true;
+            splat(&mut energy, i, 1.0);
+        }
+    }
+    loop {
+        let cluster = tightest_cluster(&pattern, &energy);
+        pattern[cluster] = false;
+        splat(&mut energy, cluster, -1.0);
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank = vec![0usize; n];
+
+    // Rank the initial points by taking them away, tightest cluster first.
+    let mut p = pattern.clone();
+    let mut e = energy.clone();
+    for r in (0..initial).rev() {
+        let cluster = tightest_cluster(&p, &e);
+        p[cluster] = false;
+        splat(&mut e, cluster, -1.0);
+        rank[cluster] = r;
+    }
+
+    // Rank the rest by filling the largest voids.
+    for r in initial..n {
+        let void = largest_void(&pattern, &energy);
+        pattern[void] = true;
+        splat(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+
+    rank.into_iter()
+        .map(|r| (r as f64 + 0.5) / n as f64)
+        .collect()
+}
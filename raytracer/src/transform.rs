@@ -0,0 +1,124 @@
+// Places an object in the scene with an affine 4x4 matrix: any mix of moving,
+// turning, scaling and shearing. The object itself is shared through its Arc, so
+// one mesh can be put down many times without copying it.
+
+use std::sync::Arc;
+
+use cgmath::{Deg, Matrix, Matrix4, Point3 as CgPoint, SquareMatrix, Transform as _, Vector3};
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{self, Point3, Vec3};
+
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    // Object to world space, and back.
+    matrix: Matrix4<f64>,
+    inverse: Matrix4<f64>,
+    // Normals go back with the inverse transpose, which keeps them
+    // perpendicular to the surface under non-uniform scaling.
+    normal_matrix: Matrix4<f64>,
+    // How much the inverse scales volumes, |det|.
+    inverse_det: f64,
+    bbox: Option<Aabb>,
+}
+
+impl Transform {
+    // Panics if `matrix` cannot be inverted.
+    pub fn new(object: Arc<dyn Hittable>, matrix: Matrix4<f64>) -> Transform {
+        let inverse = matrix.invert().expect("transform matrix is not invertible");
+
+        // The box around the moved corners of the object's box.
+        let bbox = object.bounding_box().map(|b| {
+            let (lo, hi) = (b.min(), b.max());
+            let corners = (0..8).map(|i| {
+                let pick = |bit: usize, axis: usize| if i & bit == 0 { lo[axis] } else { hi[axis] };
+                point(&matrix, Point3::new(pick(1, 0), pick(2, 1), pick(4, 2)))
+            });
+            corners
+                .map(|c| Aabb::new(c, c))
+                .reduce(|a, b| Aabb::surrounding(&a, &b))
+                .unwrap()
+        });
+
+        Transform {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+            inverse_det: inverse.determinant().abs(),
+            bbox,
+        }
+    }
+
+    pub fn matrix(&self) -> &Matrix4<f64> {
+        &self.matrix
+    }
+}
+
+pub fn translation(offset: Vec3) -> Matrix4<f64> {
+    Matrix4::from_translation(vector(offset))
+}
+
+// Counterclockwise when looking down at the tip of `axis`.
+pub fn rotation(axis: Vec3, degrees: f64) -> Matrix4<f64> {
+    Matrix4::from_axis_angle(vector(vec3::unit_vector(axis)), Deg(degrees))
+}
+
+pub fn scaling(factors: Vec3) -> Matrix4<f64> {
+    Matrix4::from_nonuniform_scale(factors.x(), factors.y(), factors.z())
+}
+
+fn vector(v: Vec3) -> Vector3<f64> {
+    Vector3::new(v.x(), v.y(), v.z())
+}
+
+fn point(m: &Matrix4<f64>, p: Point3) -> Point3 {
+    let q = m.transform_point(CgPoint::new(p.x(), p.y(), p.z()));
+    Point3::new(q.x, q.y, q.z)
+}
+
+fn direction(m: &Matrix4<f64>, v: Vec3) -> Vec3 {
+    let w = m.transform_vector(vector(v));
+    Vec3::new(w.x, w.y, w.z)
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        // The direction is not normalized again, so t means the same in both
+        // spaces and the range carries over.
+        let local = Ray::new(point(&self.inverse, r.origin()), direction(&self.inverse, r.direction()));
+        if !self.object.hit(&local, t_min, t_max, rec) {
+            return false;
+        }
+
+        // Moving the normal this way keeps its side relative to the ray, so
+        // front_face stays as it is.
+        rec.p = point(&self.matrix, rec.p);
+        rec.normal = vec3::unit_vector(direction(&self.normal_matrix, rec.normal));
+        true
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bbox
+    }
+
+    // The object's density, for the direction that maps to `dir`. Unit directions
+    // w go to B w / |B w| under the linear part B of the inverse, which stretches
+    // solid angle by |det B| / |B w|^3.
+    fn pdf_value(&self, origin: Point3, dir: Vec3) -> f64 {
+        let local = direction(&self.inverse, vec3::unit_vector(dir));
+        let length = local.length();
+        let stretch = self.inverse_det / (length * length * length);
+        self.object.pdf_value(point(&self.inverse, origin), local / length) * stretch
+    }
+
+    // A direction picked in object space, moved out again. The linear part keeps
+    // the line from the origin to the picked point, so no scaling is needed.
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let local = self.object.random(point(&self.inverse, origin), sampler);
+        direction(&self.matrix, local)
+    }
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::constants::INFINITY;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::mesh::{Face, MeshData};
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::{self, Point3, Vec3};
+
+pub struct Triangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+}
+
+impl Triangle {
+    // Stand-alone flat shaded triangle.
+    pub fn new(p0: Point3, p1: Point3, p2: Point3, m: Arc<dyn Material>) -> Triangle {
+        let mesh = MeshData {
+            positions: vec![p0, p1, p2],
+            faces: vec![Face {
+                positions: [0, 1, 2],
+                normals: None,
+                uvs: None,
+                material: 0,
+            }],
+            materials: vec![m],
+            ..Default::default()
+        };
+        Triangle::in_mesh(Arc::new(mesh), 0)
+    }
+
+    // Triangle made of face `face` of `mesh`.
+    pub fn in_mesh(mesh: Arc<MeshData>, face: usize) -> Triangle {
+        Triangle { mesh, face }
+    }
+
+    fn face(&self) -> &Face {
+        &self.mesh.faces[self.face]
+    }
+
+    fn vertices(&self) -> [Point3; 3] {
+        let [i0, i1, i2] = self.face().positions;
+        let p = &self.mesh.positions;
+        [p[i0], p[i1], p[i2]]
+    }
+}
+
+// Watertight ray/triangle intersection (Woop, Benthin and Wald, 2013).
+// The triangle is moved into a space where the ray starts at the origin and runs
+// along +z, so the edge tests are 2D and exactly consistent between neighbouring
+// triangles: a ray through a shared edge or vertex never slips between them,
+// and hits only one of them.
+// Returns the ray parameter and the barycentric weights of the three vertices.
+fn intersect(r: &Ray, p: [Point3; 3], t_min: f64, t_max: f64) -> Option<(f64, [f64; 3])> {
+    let d = r.direction();
+
+    // Make the largest direction component z, keeping the winding.
+    let kz = if d.x().abs() > d.y().abs() && d.x().abs() > d.z().abs() {
+        0
+    } else if d.y().abs() > d.z().abs() {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if d[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // Shear that maps the ray direction onto +z.
+    let sx = d[kx] / d[kz];
+    let sy = d[ky] / d[kz];
+    let sz = 1.0 / d[kz];
+
+    let a = p[0] - r.origin();
+    let b = p[1] - r.origin();
+    let c = p[2] - r.origin();
+
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    // Scaled barycentric coordinates, from the signed edge functions.
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+    // An edge function of exactly zero puts the ray on that edge. Facing the
+    // same way, neighbours run along their shared edge in opposite directions,
+    // so counting the edge for only one direction gives it to just one of them.
+    let s = det.signum();
+    let inside = |e: f64, dx: f64, dy: f64| {
+        s * e > 0.0 || (e == 0.0 && (s * dy > 0.0 || (dy == 0.0 && s * dx > 0.0)))
+    };
+    if !(inside(u, cx - bx, cy - by) && inside(v, ax - cx, ay - cy) && inside(w, bx - ax, by - ay)) {
+        return None;
+    }
+
+    let t_scaled = u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz];
+    let t = t_scaled / det;
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+
+    Some((t, [u / det, v / det, w / det]))
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64, rec: &mut HitRecord) -> bool {
+        let p = self.vertices();
+        let (t, bary) = match intersect(r, p, t_min, t_max) {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        let face = self.face();
+        let geometric_normal = vec3::unit_vector(vec3::cross(p[1] - p[0], p[2] - p[0]));
+
+        // Smooth shading: blend the vertex normals, but keep the result on the
+        // same side as the real surface.
+        let mut outward_normal = geometric_normal;
+        if let Some([n0, n1, n2]) = face.normals {
+            let n = &self.mesh.normals;
+            let shading = bary[0] * n[n0] + bary[1] * n[n1] + bary[2] * n[n2];
+            if !shading.near_zero() {
+                outward_normal = vec3::unit_vector(shading);
+                if vec3::dot(outward_normal, geometric_normal) < 0.0 {
+                    outward_normal = -outward_normal;
+                }
+            }
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.front_face = vec3::dot(r.direction(), geometric_normal) < 0.0;
+        rec.normal = if rec.front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+        // Texture coordinates from the mesh, or else the barycentric weights of
+        // the second and third vertices.
+        (rec.u, rec.v) = match face.uvs {
+            Some([t0, t1, t2]) => {
+                let uv = &self.mesh.uvs;
+                (
+                    bary[0] * uv[t0].0 + bary[1] * uv[t1].0 + bary[2] * uv[t2].0,
+                    bary[0] * uv[t0].1 + bary[1] * uv[t1].1 + bary[2] * uv[t2].1,
+                )
+            }
+            None => (bary[1], bary[2]),
+        };
+        rec.mat = Some(self.mesh.materials[face.material].clone());
+        true
+    }
+
+    // Uniform over the area, turned into solid angle seen from `origin`.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let p = self.vertices();
+        let ray = Ray::new(origin, direction);
+        let Some((t, _)) = intersect(&ray, p, 0.001, INFINITY) else {
+            return 0.0;
+        };
+        let cross = vec3::cross(p[1] - p[0], p[2] - p[0]);
+        let area = 0.5 * cross.length();
+        let to_hit = t * direction;
+        let cosine = vec3::dot(vec3::unit_vector(cross), vec3::unit_vector(direction)).abs();
+        to_hit.length_squared() / (cosine * area)
+    }
+
+    fn random(&self, origin: Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let [p0, p1, p2] = self.vertices();
+        // The square root keeps the points evenly spread instead of bunched at p0.
+        let (s, r) = sampler.next_2d();
+        let s = s.sqrt();
+        (1.0 - s) * p0 + s * (1.0 - r) * p1 + s * r * p2 - origin
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let [p0, p1, p2] = self.vertices();
+        let bbox = Aabb::surrounding(&Aabb::new(p0, p1), &Aabb::new(p2, p2));
+
+        // Pad so triangles lying in an axis plane still have some thickness.
+        const PAD: f64 = 1e-8;
+        let pad = Vec3::new(PAD, PAD, PAD);
+        Some(Aabb::new(bbox.min() - pad, bbox.max() + pad))
+    }
+}
@@ -1,8 +1,7 @@
 use std::fmt::{Display, Formatter, Result};
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub};
-use rand::Rng;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub};
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Debug, Default)]
 pub struct Vec3 {
     e: [f64; 3],
 }
@@ -56,24 +55,28 @@ impl Display for Vec3 {
     }
 }
  
-pub fn random_in_unit_sphere() -> Vec3 {
-    let mut rng = rand::thread_rng();
-    loop {
-        let p = Vec3::new(
-            rng.gen_range(-0.5..0.5),
-            rng.gen_range(-0.5..0.5),
-            rng.gen_range(-0.5..0.5),
-        );
-        if p.length_squared() < 1.0 {
-            return p;
-        }
-    }
-}
-
 pub fn reflect(v: Vec3, n: Vec3) -> Vec3 {
     v - 2.0 * dot(v, n) * n
 }
 
+// Snell's law for a unit vector `uv` crossing a surface with normal `n`.
+// `etai_over_etat` is the ratio of the refractive indices on either side.
+pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f64) -> Vec3 {
+    let cos_theta = f64::min(dot(-uv, n), 1.0);
+    let r_out_perp = etai_over_etat * (uv + cos_theta * n);
+    let r_out_parallel = -f64::sqrt(f64::abs(1.0 - r_out_perp.length_squared())) * n;
+    r_out_perp + r_out_parallel
+}
+
+// Vec3[i], 0 is x, 1 is y and 2 is z
+impl Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, i: usize) -> &f64 {
+        &self.e[i]
+    }
+}
+
 // -Vec3
 impl Neg for Vec3 {
     type Output = Vec3;
@@ -0,0 +1,98 @@
+// Blackbody colors and the light of the accretion disk: its frequency shift
+// against the closed form for a circular Schwarzschild orbit, and beaming.
+
+use raytracer::accretion_disk::AccretionDisk;
+use raytracer::blackbody::blackbody;
+use raytracer::color::{self, Color};
+use raytracer::gravity::{GravitySource, G};
+use raytracer::hittable::{HitRecord, Hittable};
+use raytracer::ray::Ray;
+use raytracer::vec3::{self, Point3, Vec3};
+
+// Mass with a Schwarzschild radius of 1.
+const MASS: f64 = 0.5 / G;
+const PEAK: f64 = 8000.0;
+
+#[test]
+fn blackbody_gets_bluer_when_hotter() {
+    // Blue is clipped to 0 for the reddest, so start below that.
+    let mut previous = -1.0;
+    for temperature in [1500.0, 2500.0, 4000.0, 6500.0, 10_000.0, 20_000.0, 40_000.0] {
+        let c = blackbody(temperature);
+        let blue_over_red = c.z() / c.x();
+        assert!(blue_over_red > previous, "{} K: {:?}", temperature, c);
+        previous = blue_over_red;
+    }
+    // Around 6500 K is close to white.
+    let c = blackbody(6500.0);
+    assert!((c.z() / c.x() - 1.0).abs() < 0.1, "{:?}", c);
+    assert_eq!(blackbody(0.0).x(), 0.0);
+}
+
+fn disk(mass: f64, peak: f64, brightness: f64) -> AccretionDisk {
+    let hole = GravitySource::black_hole(Point3::new(0.0, 0.0, 0.0), mass);
+    AccretionDisk::new(&hole, Vec3::new(0.0, 1.0, 0.0), 3.0, 12.0, peak, brightness)
+}
+
+// Light from disk point `p` reaching an observer far away in direction `to_observer`.
+fn seen(disk: &AccretionDisk, p: Point3, to_observer: Vec3) -> Color {
+    let origin = p + 100.0 * vec3::unit_vector(to_observer);
+    let ray = Ray::new(origin, p - origin);
+    let mut rec = HitRecord::new();
+    assert!(disk.hit(&ray, 0.001, f64::INFINITY, &mut rec), "{:?}", p);
+    assert!((rec.p - p).length() < 1e-9);
+    rec.mat.as_ref().unwrap().emitted(&ray, &rec)
+}
+
+// The same disk without any mass, so without shifts, with its peak temperature
+// scaled by `g`. The brightness is set so both disks turn radiance into scene
+// units the same way.
+fn unshifted(g: f64) -> AccretionDisk {
+    let brightness = color::luminance(blackbody(g * PEAK)) / color::luminance(blackbody(PEAK));
+    disk(0.0, g * PEAK, brightness)
+}
+
+fn assert_close(a: Color, b: Color) {
+    for (x, y) in [(a.x(), b.x()), (a.y(), b.y()), (a.z(), b.z())] {
+        assert!((x - y).abs() <= 1e-9 * y.abs().max(1.0), "{:?} vs {:?}", a, b);
+    }
+}
+
+#[test]
+fn redshift_matches_circular_orbit() {
+    let hole = disk(MASS, PEAK, 1.0);
+    for r in [3.0, 4.5, 8.0, 11.5] {
+        // Seen face on, the orbit is across the line of sight, so only time
+        // dilation and the climb out of the well are left:
+        // g = sqrt(1 - 3 r_s / (2 r)).
+        let p = Point3::new(r, 0.0, 0.0);
+        let g = f64::sqrt(1.0 - 1.5 / r);
+        assert_close(seen(&hole, p, Vec3::new(0.0, 1.0, 0.0)), seen(&unshifted(g), p, Vec3::new(0.0, 1.0, 0.0)));
+
+        // At an angle, the Doppler factor 1 / (1 - beta cos) comes on top, with
+        // beta = sqrt(r_s / (2 (r - r_s))) measured by a static observer there.
+        let beta = f64::sqrt(1.0 / (2.0 * (r - 1.0)));
+        for to_observer in [Vec3::new(0.0, 1.0, -1.0), Vec3::new(0.3, 0.2, 1.0)] {
+            // The gas at +x moves along -z.
+            let cos = vec3::dot(Vec3::new(0.0, 0.0, -1.0), vec3::unit_vector(to_observer));
+            let g = f64::sqrt(1.0 - 1.5 / r) / (1.0 - beta * cos);
+            assert_close(seen(&hole, p, to_observer), seen(&unshifted(g), p, to_observer));
+        }
+    }
+}
+
+#[test]
+fn approaching_side_is_brighter() {
+    let hole = disk(MASS, PEAK, 1.0);
+    // Nearly edge on, looking along +z: the gas on the +x side comes towards
+    // the observer and the gas on the -x side moves away.
+    let to_observer = Vec3::new(0.0, 0.1, -1.0);
+    for r in [3.5, 6.0, 10.0] {
+        let approaching = seen(&hole, Point3::new(r, 0.0, 0.0), to_observer);
+        let receding = seen(&hole, Point3::new(-r, 0.0, 0.0), to_observer);
+        let (a, b) = (color::luminance(approaching), color::luminance(receding));
+        assert!(a > 1.5 * b, "r = {}: {} vs {}", r, a, b);
+        // And bluer.
+        assert!(approaching.z() / approaching.x() > receding.z() / receding.x());
+    }
+}
@@ -0,0 +1,113 @@
+// The BVH must find the same closest hit as testing every object in turn, and
+// the box test under it must handle rays that start inside or run along a slab.
+
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use raytracer::aabb::Aabb;
+use raytracer::bvh::BvhNode;
+use raytracer::color::Color;
+use raytracer::cube::Cube;
+use raytracer::hittable::{HitRecord, Hittable};
+use raytracer::hittable_list::HittableList;
+use raytracer::material::{Lambertian, Material};
+use raytracer::ray::Ray;
+use raytracer::sphere::Sphere;
+use raytracer::triangle::Triangle;
+use raytracer::vec3::{Point3, Vec3};
+
+fn random_point(rng: &mut StdRng, size: f64) -> Point3 {
+    Point3::new(
+        rng.random_range(-size..size),
+        rng.random_range(-size..size),
+        rng.random_range(-size..size),
+    )
+}
+
+fn random_direction(rng: &mut StdRng) -> Vec3 {
+    loop {
+        let d = random_point(rng, 1.0);
+        let len = d.length();
+        if len > 0.01 && len <= 1.0 {
+            return d / len;
+        }
+    }
+}
+
+// Spheres, cubes and triangles scattered through a 20 unit box, each with its
+// own material so a hit can be traced back to the object.
+fn random_objects(rng: &mut StdRng) -> Vec<Arc<dyn Hittable>> {
+    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
+    for i in 0..300 {
+        let mat: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let p = random_point(rng, 10.0);
+        objects.push(match i % 3 {
+            0 => Arc::new(Sphere::new(p, rng.random_range(0.1..1.0), mat)),
+            1 => Arc::new(Cube::new(p, p + Vec3::new(0.5, 0.2, 1.0) * rng.random_range(0.2..2.0), mat)),
+            _ => Arc::new(Triangle::new(p, p + random_point(rng, 1.0), p + random_point(rng, 1.0), mat)),
+        });
+    }
+    objects
+}
+
+#[test]
+fn bvh_finds_the_same_hits_as_a_list() {
+    let mut rng = StdRng::seed_from_u64(9);
+    let objects = random_objects(&mut rng);
+    let mut list = HittableList::new();
+    for object in &objects {
+        list.add(object.clone());
+    }
+    let bvh = BvhNode::new(objects);
+
+    let mut hits = 0;
+    for _ in 0..20_000 {
+        // Aimed into the cloud of objects, from inside or outside it.
+        let origin = random_point(&mut rng, 15.0);
+        let ray = Ray::new(origin, random_point(&mut rng, 10.0) - origin);
+        let mut expected = HitRecord::new();
+        let mut found = HitRecord::new();
+        let hit = list.hit(&ray, 0.001, f64::INFINITY, &mut expected);
+        assert_eq!(bvh.hit(&ray, 0.001, f64::INFINITY, &mut found), hit);
+        if !hit {
+            continue;
+        }
+        hits += 1;
+        assert_eq!(found.t, expected.t);
+        assert!(Arc::ptr_eq(found.mat.as_ref().unwrap(), expected.mat.as_ref().unwrap()));
+    }
+    // Enough of the rays hit something for the comparison to mean anything.
+    assert!(hits > 5_000, "{}", hits);
+}
+
+#[test]
+fn box_hit_from_inside() {
+    let bbox = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+    let mut rng = StdRng::seed_from_u64(3);
+    for _ in 0..1000 {
+        let ray = Ray::new(random_point(&mut rng, 0.9), random_direction(&mut rng));
+        assert!(bbox.hit(&ray, 0.001, f64::INFINITY));
+        // Even with the range limited to just past the start.
+        assert!(bbox.hit(&ray, 0.0, 1e-6));
+    }
+}
+
+#[test]
+fn box_hit_parallel_to_a_slab() {
+    let bbox = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    // Runs along the y and z slabs, inside both.
+    assert!(bbox.hit(&Ray::new(Point3::new(-5.0, 0.5, -0.5), x), 0.001, f64::INFINITY));
+    // Outside the y slab, so it never gets in, whichever way the zero points.
+    assert!(!bbox.hit(&Ray::new(Point3::new(-5.0, 1.5, 0.0), x), 0.001, f64::INFINITY));
+    assert!(!bbox.hit(&Ray::new(Point3::new(-5.0, 1.5, 0.0), Vec3::new(1.0, -0.0, 0.0)), 0.001, f64::INFINITY));
+    assert!(!bbox.hit(&Ray::new(Point3::new(-5.0, 0.0, -1.5), x), 0.001, f64::INFINITY));
+    // Along an axis but pointing away from the box.
+    assert!(!bbox.hit(&Ray::new(Point3::new(-5.0, 0.0, 0.0), -x), 0.001, f64::INFINITY));
+    // A box with no depth still gets hit through its face.
+    let flat = Aabb::new(Point3::new(-1.0, -1.0, 0.0), Point3::new(1.0, 1.0, 0.0));
+    let z = Vec3::new(0.0, 0.0, -1.0);
+    assert!(flat.hit(&Ray::new(Point3::new(0.2, 0.3, 5.0), z), 0.001, f64::INFINITY));
+}
@@ -0,0 +1,127 @@
+// Where the camera's rays start and which way they go.
+
+use raytracer::camera::{Camera, Lens};
+use raytracer::sampler::{IndependentSampler, Sampler};
+use raytracer::vec3::{self, Point3, Vec3};
+
+fn close(a: Vec3, b: Vec3) -> bool {
+    (a - b).length() < 1e-9
+}
+
+// A few placements, including one looking straight down with a tilted up vector.
+fn placements() -> [(Point3, Point3, Vec3); 4] {
+    [
+        (Point3::new(0.0, 0.0, 2.0), Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 1.0, 0.0)),
+        (Point3::new(3.0, 1.0, -2.0), Point3::new(-1.0, 0.5, 4.0), Vec3::new(0.0, 1.0, 0.0)),
+        (Point3::new(1.0, 5.0, 1.0), Point3::new(1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 1.0)),
+        (Point3::new(-2.0, 2.0, 2.0), Point3::new(0.0, 0.0, 0.0), Vec3::new(0.3, 1.0, -0.2)),
+    ]
+}
+
+#[test]
+fn center_ray_points_at_look_at() {
+    let mut sampler = IndependentSampler::new(1);
+    for (from, at, vup) in placements() {
+        let camera = Camera::look_at(from, at, vup, 40.0, 1.5);
+        let r = camera.get_ray(0.5, 0.5, &mut sampler);
+        assert!(close(r.origin(), from));
+        assert!(close(vec3::unit_vector(r.direction()), vec3::unit_vector(at - from)), "{:?}", r.direction());
+    }
+}
+
+#[test]
+fn vup_is_up_in_the_image() {
+    let mut sampler = IndependentSampler::new(1);
+    for (from, at, vup) in placements() {
+        let (vfov, aspect) = (60.0, 2.0);
+        let camera = Camera::look_at(from, at, vup, vfov, aspect);
+        let forward = vec3::unit_vector(at - from);
+        // Up in the image is vup with the part along the view taken out, and
+        // right is across both.
+        let up = vec3::unit_vector(vup - vec3::dot(vup, forward) * forward);
+        let right = vec3::cross(forward, up);
+
+        let mut direction = |s: f64, t: f64| vec3::unit_vector(camera.get_ray(s, t, &mut sampler).direction());
+        // The middle of the top edge is half the field of view up, and the
+        // middle of the right edge is as far right as the aspect ratio allows.
+        let half = f64::tan(vfov.to_radians() / 2.0);
+        assert!(close(direction(0.5, 1.0), vec3::unit_vector(forward + half * up)));
+        assert!(close(direction(0.5, 0.0), vec3::unit_vector(forward - half * up)));
+        assert!(close(direction(1.0, 0.5), vec3::unit_vector(forward + aspect * half * right)));
+        assert!(close(direction(0.0, 0.5), vec3::unit_vector(forward - aspect * half * right)));
+        assert!(close(
+            direction(1.0, 1.0),
+            vec3::unit_vector(forward + aspect * half * right + half * up)
+        ));
+    }
+}
+
+#[test]
+fn pinhole_rays_start_at_look_from() {
+    let mut sampler = IndependentSampler::new(2);
+    for (from, at, vup) in placements() {
+        let lens = Lens {
+            aperture_radius: 0.0,
+            focus_dist: 3.0,
+            blades: 6,
+            rotation: 0.0,
+        };
+        let camera = Camera::with_lens(from, at, vup, 50.0, 1.0, lens);
+        for i in 0..200 {
+            sampler.start_sample(i, 0, 0);
+            let (s, t) = sampler.next_2d();
+            let r = camera.get_ray(s, t, &mut sampler);
+            assert_eq!((r.origin().x(), r.origin().y(), r.origin().z()), (from.x(), from.y(), from.z()));
+        }
+    }
+}
+
+#[test]
+fn lens_samples_stay_inside_the_aperture() {
+    let mut sampler = IndependentSampler::new(3);
+    for (from, at, vup) in placements() {
+        let forward = vec3::unit_vector(at - from);
+        let up = vec3::unit_vector(vup - vec3::dot(vup, forward) * forward);
+        let right = vec3::cross(forward, up);
+
+        for (blades, rotation) in [(0, 0.0), (3, 0.0), (5, 18.0), (6, 30.0)] {
+            let lens = Lens {
+                aperture_radius: 0.4,
+                focus_dist: 2.5,
+                blades,
+                rotation,
+            };
+            let camera = Camera::with_lens(from, at, vup, 50.0, 1.0, lens);
+            let mut farthest = 0.0f64;
+            for i in 0..5000 {
+                sampler.start_sample(i, 0, 0);
+                let (s, t) = sampler.next_2d();
+                let r = camera.get_ray(s, t, &mut sampler);
+                // The lens is across the view at look_from.
+                let offset = r.origin() - from;
+                assert!(vec3::dot(offset, forward).abs() < 1e-9);
+                let (x, y) = (vec3::dot(offset, right) / 0.4, vec3::dot(offset, up) / 0.4);
+                farthest = farthest.max(x.hypot(y));
+                assert!(x.hypot(y) <= 1.0 + 1e-9, "{} blades: ({}, {})", blades, x, y);
+
+                // Inside every edge of the polygon: the edge between corners k
+                // and k + 1 is cos(pi / n) from the center, facing half way
+                // between them.
+                for k in 0..blades {
+                    let angle = rotation.to_radians() + (2.0 * k as f64 + 1.0) * std::f64::consts::PI / blades as f64;
+                    let reach = x * angle.cos() + y * angle.sin();
+                    let limit = (std::f64::consts::PI / blades as f64).cos();
+                    assert!(reach <= limit + 1e-9, "{} blades, edge {}: ({}, {})", blades, k, x, y);
+                }
+
+                // Wherever it starts on the lens, the ray meets the same point
+                // on the focus plane as the ray through the center.
+                let pinhole = Camera::look_at(from, at, vup, 50.0, 1.0).get_ray(s, t, &mut sampler);
+                let focus = |o: Point3, d: Vec3| o + (2.5 / vec3::dot(d, forward)) * d;
+                assert!(close(focus(r.origin(), r.direction()), focus(pinhole.origin(), pinhole.direction())));
+            }
+            // And it covers the aperture out to the corners.
+            assert!(farthest > 0.97, "{} blades: {}", blades, farthest);
+        }
+    }
+}
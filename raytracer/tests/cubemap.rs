@@ -0,0 +1,96 @@
+// Which cube map face, and which way up, each direction is looked up on.
+
+use raytracer::color::Color;
+use raytracer::environment::{CubeMap, Environment};
+use raytracer::image::ImageBuffer;
+use raytracer::vec3::{self, Vec3};
+
+const SIZE: usize = 4;
+
+// Face `index` as a 4x4 image holding the face number in red, x in green and y
+// in blue, so a lookup tells where on which face it landed.
+fn face(index: usize) -> ImageBuffer {
+    let mut image = ImageBuffer::new(SIZE, SIZE);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            image.set(x, y, Color::new(index as f64, x as f64, y as f64));
+        }
+    }
+    image
+}
+
+// For each face in `CubeMap` order, the direction through its center and the
+// directions of its image's right and down seen from inside the cube. Right is
+// the view direction crossed with up; the top and bottom faces are upright when
+// tipping your head up or down from the front face (-z).
+fn axes() -> [(Vec3, Vec3, Vec3); 6] {
+    let (x, y, z) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+    let frame = |forward: Vec3, up: Vec3| (forward, vec3::cross(forward, up), -up);
+    [
+        frame(x, y),
+        frame(-x, y),
+        frame(y, z),
+        frame(-y, -z),
+        frame(-z, y),
+        frame(z, y),
+    ]
+}
+
+fn check_faces(map: &CubeMap) {
+    for (index, (forward, right, down)) in axes().into_iter().enumerate() {
+        let center = map.color(forward);
+        assert_eq!(center.x(), index as f64, "face along {:?}", forward);
+        // The middle of a 4x4 face is between its pixels 1 and 2.
+        assert!((center.y() - 1.5).abs() < 1e-9 && (center.z() - 1.5).abs() < 1e-9, "{:?}", center);
+
+        // Half way to the edge is a quarter of the face, a pixel, further on.
+        let to_right = map.color(forward + 0.5 * right);
+        assert_eq!(to_right.x(), index as f64);
+        assert!((to_right.y() - 2.5).abs() < 1e-9 && (to_right.z() - 1.5).abs() < 1e-9, "{:?}", to_right);
+        let lower = map.color(forward + 0.5 * down);
+        assert_eq!(lower.x(), index as f64);
+        assert!((lower.y() - 1.5).abs() < 1e-9 && (lower.z() - 2.5).abs() < 1e-9, "{:?}", lower);
+    }
+}
+
+#[test]
+fn faces_are_looked_up_upright() {
+    let map = CubeMap::new([0, 1, 2, 3, 4, 5].map(face), 0.0, 1.0);
+    check_faces(&map);
+}
+
+#[test]
+fn cross_layouts_unfold_to_the_same_faces() {
+    // (column, row) of right, left, top, bottom, front and back.
+    let horizontal = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+    let vertical = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)];
+    for (cells, columns, rows) in [(horizontal, 4, 3), (vertical, 3, 4)] {
+        let mut cross = ImageBuffer::new(columns * SIZE, rows * SIZE);
+        for (index, (col, row)) in cells.into_iter().enumerate() {
+            let image = face(index);
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    // Below the bottom face, the back face hangs upside down.
+                    let (cx, cy) = if rows == 4 && index == 5 { (SIZE - 1 - x, SIZE - 1 - y) } else { (x, y) };
+                    cross.set(col * SIZE + cx, row * SIZE + cy, image.get(x, y));
+                }
+            }
+        }
+        check_faces(&CubeMap::from_cross(&cross, 0.0, 1.0).unwrap());
+    }
+    assert!(CubeMap::from_cross(&ImageBuffer::new(8, 8), 0.0, 1.0).is_none());
+}
+
+#[test]
+fn rotation_and_intensity() {
+    // Turned a quarter of the way round, the face that was on the right is in
+    // front or behind, and everything is twice as bright.
+    let map = CubeMap::new([0, 1, 2, 3, 4, 5].map(face), 90.0, 2.0);
+    let plain = CubeMap::new([0, 1, 2, 3, 4, 5].map(face), 0.0, 1.0);
+    let right = plain.color(Vec3::new(1.0, 0.0, 0.0));
+    let turned = [Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 0.0, 1.0)]
+        .map(|d| map.color(d));
+    assert!(turned.iter().any(|c| c.x() == 2.0 * right.x()), "{:?}", turned);
+    // Up stays up.
+    assert_eq!(map.color(Vec3::new(0.0, 1.0, 0.0)).x(), 2.0 * 2.0);
+}
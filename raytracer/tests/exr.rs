@@ -0,0 +1,188 @@
+// Checks the OpenEXR writer's output byte by byte: the header attributes, the
+// offset table, the pixels, and ZIP chunks that unpack to the plain scanlines.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use raytracer::color::Color;
+use raytracer::exr::{self, ExrCompression, ExrPixelType};
+use raytracer::image::ImageBuffer;
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn i32_at(data: &[u8], at: usize) -> i32 {
+    i32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+// A zero terminated string starting at `at`, and the position after it.
+fn name_at(data: &[u8], at: usize) -> (String, usize) {
+    let end = at + data[at..].iter().position(|&b| b == 0).unwrap();
+    (String::from_utf8(data[at..end].to_vec()).unwrap(), end + 1)
+}
+
+struct Exr {
+    // Attribute name to (type, value).
+    attributes: BTreeMap<String, (String, Vec<u8>)>,
+    // First scanline and data of each chunk, found through the offset table.
+    chunks: Vec<(i32, Vec<u8>)>,
+}
+
+fn parse(data: &[u8], lines_per_chunk: usize, height: usize) -> Exr {
+    assert_eq!(u32_at(data, 0), 20000630, "magic number");
+    assert_eq!(u32_at(data, 4), 2, "version and flags");
+
+    let mut attributes = BTreeMap::new();
+    let mut at = 8;
+    loop {
+        let (name, next) = name_at(data, at);
+        if name.is_empty() {
+            at = next;
+            break;
+        }
+        let (kind, next) = name_at(data, next);
+        let size = i32_at(data, next) as usize;
+        let value = data[next + 4..next + 4 + size].to_vec();
+        attributes.insert(name, (kind, value));
+        at = next + 4 + size;
+    }
+
+    let count = height.div_ceil(lines_per_chunk);
+    let mut chunks = Vec::new();
+    let mut end = at + 8 * count;
+    for i in 0..count {
+        let offset = u64::from_le_bytes(data[at + 8 * i..at + 8 * i + 8].try_into().unwrap()) as usize;
+        // Chunks follow the table in order, with nothing between them.
+        assert_eq!(offset, end, "offset of chunk {}", i);
+        let y = i32_at(data, offset);
+        let size = i32_at(data, offset + 4) as usize;
+        chunks.push((y, data[offset + 8..offset + 8 + size].to_vec()));
+        end = offset + 8 + size;
+    }
+    assert_eq!(end, data.len(), "bytes after the last chunk");
+    Exr { attributes, chunks }
+}
+
+fn write(image: &ImageBuffer, pixel_type: ExrPixelType, compression: ExrCompression) -> Vec<u8> {
+    let mut data = Vec::new();
+    exr::write_exr(image, pixel_type, compression, &mut data).unwrap();
+    data
+}
+
+// Values that halves hold exactly, different in every pixel and channel.
+fn test_image(width: usize, height: usize) -> ImageBuffer {
+    let pixels = (0..width * height)
+        .map(|i| {
+            let v = (i % 64) as f64 * 0.25;
+            Color::new(v, v + 0.5, 16.0 - v)
+        })
+        .collect();
+    ImageBuffer::from_pixels(width, height, pixels)
+}
+
+fn check_header(exr: &Exr, pixel_type: ExrPixelType, compression: u8, width: i32, height: i32) {
+    let attribute = |name: &str| exr.attributes.get(name).unwrap_or_else(|| panic!("no {}", name));
+
+    let (kind, channels) = attribute("channels");
+    assert_eq!(kind, "chlist");
+    let type_id = match pixel_type {
+        ExrPixelType::Half => 1,
+        ExrPixelType::Float => 2,
+    };
+    let mut expected = Vec::new();
+    for name in ["B", "G", "R"] {
+        expected.extend_from_slice(name.as_bytes());
+        expected.push(0);
+        expected.extend_from_slice(&i32::to_le_bytes(type_id));
+        expected.extend_from_slice(&[0, 0, 0, 0]);
+        expected.extend_from_slice(&1i32.to_le_bytes());
+        expected.extend_from_slice(&1i32.to_le_bytes());
+    }
+    expected.push(0);
+    assert_eq!(channels, &expected);
+
+    assert_eq!(attribute("compression"), &("compression".to_string(), vec![compression]));
+    let window: Vec<u8> = [0, 0, width - 1, height - 1].iter().flat_map(|v: &i32| v.to_le_bytes()).collect();
+    assert_eq!(attribute("dataWindow"), &("box2i".to_string(), window.clone()));
+    assert_eq!(attribute("displayWindow"), &("box2i".to_string(), window));
+    assert_eq!(attribute("lineOrder"), &("lineOrder".to_string(), vec![0]));
+    assert_eq!(attribute("pixelAspectRatio"), &("float".to_string(), 1.0f32.to_le_bytes().to_vec()));
+    assert_eq!(attribute("screenWindowCenter"), &("v2f".to_string(), vec![0; 8]));
+    assert_eq!(attribute("screenWindowWidth"), &("float".to_string(), 1.0f32.to_le_bytes().to_vec()));
+    assert_eq!(exr.attributes.len(), 8);
+}
+
+#[test]
+fn uncompressed_half_and_float() {
+    let image = test_image(5, 3);
+    for pixel_type in [ExrPixelType::Half, ExrPixelType::Float] {
+        let exr = parse(&write(&image, pixel_type, ExrCompression::None), 1, 3);
+        check_header(&exr, pixel_type, 0, 5, 3);
+
+        // One scanline per chunk, each holding all of B, then G, then R.
+        for (y, (first, data)) in exr.chunks.iter().enumerate() {
+            assert_eq!(*first, y as i32);
+            let size = if pixel_type == ExrPixelType::Half { 2 } else { 4 };
+            assert_eq!(data.len(), 3 * 5 * size);
+            for (k, channel) in [2, 1, 0].into_iter().enumerate() {
+                for x in 0..5 {
+                    let at = (k * 5 + x) * size;
+                    let expected = image.get(x, y)[channel] as f32;
+                    let (value, expected) = match pixel_type {
+                        ExrPixelType::Half => (
+                            u16::from_le_bytes([data[at], data[at + 1]]) as u32,
+                            exr::f32_to_half(expected) as u32,
+                        ),
+                        ExrPixelType::Float => (u32_at(data, at), expected.to_bits()),
+                    };
+                    assert_eq!(value, expected, "({}, {}) channel {}", x, y, channel);
+                }
+            }
+        }
+    }
+    // A few halves checked by hand.
+    assert_eq!(exr::f32_to_half(0.5), 0x3800);
+    assert_eq!(exr::f32_to_half(1.0), 0x3c00);
+    assert_eq!(exr::f32_to_half(16.0), 0x4c00);
+    assert_eq!(exr::f32_to_half(-2.0), 0xc000);
+}
+
+// Undo EXR's ZIP scheme: inflate, turn the differences back into bytes, then
+// interleave the two halves again.
+fn zip_decompress(packed: &[u8]) -> Vec<u8> {
+    let mut shuffled = Vec::new();
+    ZlibDecoder::new(packed).read_to_end(&mut shuffled).unwrap();
+    for i in 1..shuffled.len() {
+        shuffled[i] = shuffled[i - 1].wrapping_add(shuffled[i]).wrapping_sub(128);
+    }
+    let half = shuffled.len().div_ceil(2);
+    (0..shuffled.len())
+        .map(|i| if i % 2 == 0 { shuffled[i / 2] } else { shuffled[half + i / 2] })
+        .collect()
+}
+
+#[test]
+fn zip_chunks_unpack_to_the_scanlines() {
+    // Two chunks of 16 lines, the second one short.
+    let image = test_image(24, 20);
+    for pixel_type in [ExrPixelType::Half, ExrPixelType::Float] {
+        let plain = parse(&write(&image, pixel_type, ExrCompression::None), 1, 20);
+        let zip = parse(&write(&image, pixel_type, ExrCompression::Zip), 16, 20);
+        check_header(&zip, pixel_type, 3, 24, 20);
+
+        assert_eq!(zip.chunks.len(), 2);
+        for (i, (first, packed)) in zip.chunks.iter().enumerate() {
+            assert_eq!(*first, 16 * i as i32);
+            let rows: Vec<u8> = plain.chunks[16 * i..(16 * i + 16).min(20)]
+                .iter()
+                .flat_map(|(_, data)| data.clone())
+                .collect();
+            // This image is regular enough that every chunk shrinks.
+            assert!(packed.len() < rows.len());
+            assert_eq!(zip_decompress(packed), rows, "chunk {}", i);
+        }
+    }
+}
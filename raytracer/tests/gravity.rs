@@ -0,0 +1,47 @@
+// Several gravity sources at once: their pulls add up, so symmetric masses
+// cancel on the line between them.
+
+use raytracer::gravity::{self, GravitySource, G};
+use raytracer::integrator::{integrate_ray_path, Marching};
+use raytracer::ray::Ray;
+use raytracer::vec3::{self, Point3, Vec3};
+
+// Mass with a Schwarzschild radius of 1.
+const MASS: f64 = 0.5 / G;
+
+// Angle between the starting and final direction of a ray along +x through
+// (-50, y, 0).
+fn deflection(sources: &[GravitySource], y: f64) -> f64 {
+    let r = Ray::new(Point3::new(-50.0, y, 0.0), Vec3::new(1.0, 0.0, 0.0));
+    let march = Marching {
+        max_time: 100.0,
+        max_step: 5.0,
+        tolerance: 1e-6,
+    };
+    let dir = integrate_ray_path(&r, sources, &march).unwrap().direction();
+    f64::atan2(vec3::cross(r.direction(), dir).length(), vec3::dot(r.direction(), dir))
+}
+
+#[test]
+fn symmetric_masses_cancel_on_the_midline() {
+    for make in [GravitySource::new, GravitySource::black_hole] {
+        let above = make(Point3::new(0.0, 10.0, 0.0), MASS);
+        let below = make(Point3::new(0.0, -10.0, 0.0), MASS);
+
+        // Either mass alone turns the ray by around r_s / b = 0.1 (Newtonian) or
+        // twice that (Schwarzschild).
+        assert!(deflection(&[above], 0.0) > 0.09);
+        assert!(deflection(&[below], 0.0) > 0.09);
+        // Together they pull equally both ways.
+        let both = [above, below];
+        assert!(deflection(&both, 0.0) < 1e-9, "{}", deflection(&both, 0.0));
+
+        // Anywhere on the midline the momentum only changes along the line.
+        for x in [-30.0, -1.0, 0.0, 2.5, 40.0] {
+            let pos = Point3::new(x, 0.0, 0.0);
+            let mom = gravity::initial_momentum(&both, pos, Vec3::new(1.0, 0.0, 0.0));
+            let (_, force) = gravity::derivative(&both, pos, mom);
+            assert!(force.y().abs() < 1e-12 && force.z().abs() < 1e-12, "{:?} at x = {}", force, x);
+        }
+    }
+}
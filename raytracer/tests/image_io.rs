@@ -0,0 +1,184 @@
+// Round trips through the image writers and readers.
+
+use std::fs;
+use std::path::PathBuf;
+
+use raytracer::color::Color;
+use raytracer::image::{ImageBuffer, LdrImage};
+use raytracer::image_io;
+use raytracer::tonemap;
+
+// read_image goes by the file name, so tests write real files.
+fn temp_file(name: &str, data: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("raytracer-{}-{}", std::process::id(), name));
+    fs::write(&path, data).unwrap();
+    path
+}
+
+fn read_back(name: &str, data: &[u8]) -> std::io::Result<ImageBuffer> {
+    let path = temp_file(name, data);
+    let image = image_io::read_image(&path);
+    fs::remove_file(&path).unwrap();
+    image
+}
+
+// A 3x2 image with a different value in every channel.
+fn test_image() -> ImageBuffer {
+    let pixels = (0..6)
+        .map(|i| Color::new(i as f64 * 0.5, i as f64 * 0.25 + 0.125, 10.0 - i as f64))
+        .collect();
+    ImageBuffer::from_pixels(3, 2, pixels)
+}
+
+fn channels(image: &ImageBuffer) -> Vec<[f64; 3]> {
+    image.pixels().iter().map(|c| [c.x(), c.y(), c.z()]).collect()
+}
+
+fn test_ldr() -> LdrImage {
+    LdrImage {
+        width: 3,
+        height: 2,
+        max_value: 255,
+        data: (0..18).map(|i| i * 15).collect(),
+    }
+}
+
+fn check_ldr(read: &ImageBuffer, ldr: &LdrImage) {
+    assert_eq!((read.width(), read.height()), (ldr.width, ldr.height));
+    for (i, c) in read.pixels().iter().enumerate() {
+        for (k, v) in [c.x(), c.y(), c.z()].into_iter().enumerate() {
+            let expected = tonemap::srgb_decode(ldr.data[3 * i + k] as f64 / ldr.max_value as f64);
+            assert!((v - expected).abs() < 1e-12, "pixel {} channel {}: {} vs {}", i, k, v, expected);
+        }
+    }
+}
+
+#[test]
+fn hdr_keeps_extreme_values() {
+    let values = [0.0, 1e-40, 1.0, 1e30, f64::INFINITY];
+    let pixels = values.iter().map(|&v| Color::new(v, v, v)).collect();
+    let image = ImageBuffer::from_pixels(values.len(), 1, pixels);
+
+    let mut data = Vec::new();
+    image_io::write_hdr(&image, &mut data).unwrap();
+    let read = image_io::read_hdr(&data).unwrap();
+    assert_eq!((read.width(), read.height()), (values.len(), 1));
+
+    let back: Vec<f64> = (0..values.len()).map(|x| read.get(x, 0).x()).collect();
+    // Too dark for the format, so black.
+    assert_eq!(back[0], 0.0);
+    assert_eq!(back[1], 0.0);
+    // Eight bit mantissas are good to about 1%.
+    assert!((back[2] - 1.0).abs() < 0.01, "{}", back[2]);
+    assert!((back[3] / 1e30 - 1.0).abs() < 0.01, "{}", back[3]);
+    // Infinity becomes the brightest value RGBE can hold, not a dark one.
+    assert!(back[4].is_finite() && back[4] > 1e38, "{}", back[4]);
+    for x in 0..values.len() {
+        let c = read.get(x, 0);
+        assert!(c.x() == c.y() && c.y() == c.z());
+    }
+}
+
+#[test]
+fn pfm_round_trips() {
+    let image = test_image();
+    let mut data = Vec::new();
+    image_io::write_pfm(&image, &mut data).unwrap();
+    let read = read_back("round-trip.pfm", &data).unwrap();
+    assert_eq!((read.width(), read.height()), (3, 2));
+    // The values are exact in f32, so they come back unchanged.
+    assert_eq!(channels(&read), channels(&image));
+}
+
+#[test]
+fn pfm_reads_big_endian_bottom_to_top() {
+    // A positive scale means big-endian, and the first row stored is the bottom one.
+    let mut data = b"Pf\n2 2\n1.0\n".to_vec();
+    for v in [1.0f32, 2.0, 3.0, 4.0] {
+        data.extend_from_slice(&v.to_be_bytes());
+    }
+    let read = read_back("big-endian.pfm", &data).unwrap();
+    let gray: Vec<f64> = read.pixels().iter().map(|c| c.x()).collect();
+    assert_eq!(gray, [3.0, 4.0, 1.0, 2.0]);
+    assert!(read.pixels().iter().all(|c| c.x() == c.y() && c.y() == c.z()));
+}
+
+#[test]
+fn ppm_round_trips() {
+    let ldr = test_ldr();
+    let mut p3 = Vec::new();
+    image_io::write_p3(&ldr, &mut p3).unwrap();
+    check_ldr(&read_back("round-trip.ppm", &p3).unwrap(), &ldr);
+
+    let mut p6 = Vec::new();
+    image_io::write_p6(&ldr, &mut p6).unwrap();
+    check_ldr(&read_back("round-trip.pnm", &p6).unwrap(), &ldr);
+}
+
+#[test]
+fn bad_sizes_are_errors() {
+    let huge = format!("{} {}", usize::MAX / 2, 3);
+    let headers = [
+        ("zero-width.pfm", "PF\n0 2\n-1.0\n".to_string()),
+        ("zero-height.pfm", "PF\n2 0\n-1.0\n".to_string()),
+        ("huge.pfm", format!("PF\n{}\n-1.0\n", huge)),
+        ("zero-width.ppm", "P6\n0 2\n255\n".to_string()),
+        ("zero-height.ppm", "P3\n2 0\n255\n".to_string()),
+        ("huge-p6.ppm", format!("P6\n{}\n255\n", huge)),
+        ("huge-p3.ppm", format!("P3\n{}\n255\n0 0 0\n", huge)),
+        // Fits in usize as a sample count but not as a byte count.
+        ("huge-bytes.pfm", format!("Pf\n{} 1\n-1.0\n", usize::MAX / 3)),
+        ("truncated.pfm", "PF\n2 2\n-1.0\n0000".to_string()),
+    ];
+    for (name, header) in headers {
+        let err = match read_back(name, header.as_bytes()) {
+            Ok(_) => panic!("{} read without an error", name),
+            Err(e) => e,
+        };
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{}: {}", name, err);
+    }
+}
+
+#[test]
+fn pfm_writes_little_endian_bottom_row_first() {
+    let image = test_image();
+    let mut data = Vec::new();
+    image_io::write_pfm(&image, &mut data).unwrap();
+
+    let header = b"PF\n3 2\n-1.0\n";
+    assert_eq!(&data[..header.len()], header);
+    let floats: Vec<f32> = data[header.len()..]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+    let expected: Vec<f32> = [1, 0]
+        .iter()
+        .flat_map(|&y| (0..3).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let c = image.get(x, y);
+            [c.x() as f32, c.y() as f32, c.z() as f32]
+        })
+        .collect();
+    assert_eq!(floats, expected);
+}
+
+#[test]
+fn png_and_p6_round_trip() {
+    let ldr = test_ldr();
+    let mut png = Vec::new();
+    image_io::write_png(&ldr, &mut png).unwrap();
+    check_ldr(&read_back("round-trip.png", &png).unwrap(), &ldr);
+
+    // 16 bit samples, in both formats.
+    let wide = LdrImage {
+        max_value: 65535,
+        data: ldr.data.iter().map(|&v| v * 256 + 7).collect(),
+        ..ldr
+    };
+    let mut png = Vec::new();
+    image_io::write_png(&wide, &mut png).unwrap();
+    check_ldr(&read_back("round-trip-16.png", &png).unwrap(), &wide);
+    let mut p6 = Vec::new();
+    image_io::write_p6(&wide, &mut p6).unwrap();
+    check_ldr(&read_back("round-trip-16.ppm", &p6).unwrap(), &wide);
+}
@@ -0,0 +1,100 @@
+// Shadow of a spinning black hole, checked against Bardeen's analytic boundary.
+
+use raytracer::gravity::{GravitySource, G};
+use raytracer::integrator::{integrate_ray_path, Marching};
+use raytracer::ray::Ray;
+use raytracer::vec3::{self, Point3, Vec3};
+
+// Mass of 1 in geometric units, so lengths are in units of M.
+const MASS: f64 = 1.0 / G;
+const DISTANCE: f64 = 1000.0;
+
+// Whether the ray reaching a far away observer at `inclination` from the spin axis
+// (z) at image plane coordinates (alpha, beta) came from the hole. Beta points
+// along the projected spin axis and alpha to the right of it.
+fn in_shadow(spin: f64, inclination: f64, alpha: f64, beta: f64) -> bool {
+    let hole = GravitySource::kerr(Point3::new(0.0, 0.0, 0.0), MASS, spin, Vec3::new(0.0, 0.0, 1.0));
+    let to_observer = Vec3::new(inclination.sin(), 0.0, inclination.cos());
+    let up = Vec3::new(-inclination.cos(), 0.0, inclination.sin());
+    let right = vec3::cross(up, to_observer);
+
+    let origin = DISTANCE * to_observer + alpha * right + beta * up;
+    let r = Ray::new(origin, -to_observer);
+    let march = Marching {
+        max_time: 2.0 * DISTANCE + 500.0,
+        max_step: 50.0,
+        tolerance: 1e-6,
+    };
+    integrate_ray_path(&r, &[hole], &march).is_none()
+}
+
+// Points (alpha, beta) on the shadow boundary, from the spherical photon orbits.
+fn shadow_boundary(a: f64, inclination: f64, samples: usize) -> Vec<(f64, f64)> {
+    let r1 = 2.0 * (1.0 + f64::cos(2.0 / 3.0 * f64::acos(-a)));
+    let r2 = 2.0 * (1.0 + f64::cos(2.0 / 3.0 * f64::acos(a)));
+    let mut points = Vec::new();
+    for k in 0..samples {
+        let r = r1 + (r2 - r1) * (k as f64 + 0.5) / samples as f64;
+        let xi = -(r * r * r - 3.0 * r * r + a * a * r + a * a) / (a * (r - 1.0));
+        let eta = r * r * r * (4.0 * a * a - r * (r - 3.0) * (r - 3.0)) / (a * a * (r - 1.0) * (r - 1.0));
+        let alpha = -xi / inclination.sin();
+        let beta2 = eta + a * a * inclination.cos().powi(2) - xi * xi / inclination.tan().powi(2);
+        if beta2 < 0.0 {
+            continue;
+        }
+        points.push((alpha, beta2.sqrt()));
+        points.push((alpha, -beta2.sqrt()));
+    }
+    points
+}
+
+fn check_boundary(spin: f64, inclination: f64) {
+    for (alpha, beta) in shadow_boundary(spin, inclination, 8) {
+        assert!(
+            in_shadow(spin, inclination, 0.97 * alpha, 0.97 * beta),
+            "({}, {}) should be inside the shadow",
+            alpha,
+            beta
+        );
+        assert!(
+            !in_shadow(spin, inclination, 1.03 * alpha, 1.03 * beta),
+            "({}, {}) should be outside the shadow",
+            alpha,
+            beta
+        );
+    }
+}
+
+#[test]
+fn shadow_matches_analytic_boundary_edge_on() {
+    check_boundary(0.9, 90f64.to_radians());
+}
+
+#[test]
+fn shadow_matches_analytic_boundary_inclined() {
+    check_boundary(0.6, 45f64.to_radians());
+}
+
+#[test]
+fn shadow_is_shifted_away_from_the_approaching_side() {
+    // Seen edge-on with a = 0.9 the boundary lies near alpha = -2.9 on the side
+    // turning towards the observer and alpha = 6.9 on the other, where a
+    // non-spinning hole has 3 * sqrt(3) = 5.2 on both.
+    let i = 90f64.to_radians();
+    assert!(!in_shadow(0.9, i, -3.5, 0.0));
+    assert!(in_shadow(0.9, i, 6.0, 0.0));
+    assert!(in_shadow(0.0, i, -5.0, 0.0));
+    assert!(!in_shadow(0.0, i, 6.0, 0.0));
+}
+
+#[test]
+fn non_spinning_shadow_is_a_circle() {
+    let i = 60f64.to_radians();
+    let critical = 3.0 * f64::sqrt(3.0);
+    for k in 0..6 {
+        let angle = k as f64 * std::f64::consts::PI / 3.0;
+        let (alpha, beta) = (angle.cos(), angle.sin());
+        assert!(in_shadow(0.0, i, 0.98 * critical * alpha, 0.98 * critical * beta));
+        assert!(!in_shadow(0.0, i, 1.02 * critical * alpha, 1.02 * critical * beta));
+    }
+}
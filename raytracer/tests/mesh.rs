@@ -0,0 +1,198 @@
+// Triangles and the OBJ loader: no gaps or double hits between neighbours,
+// interpolated shading data, and how faces, indices and materials are read.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use raytracer::color::Color;
+use raytracer::hittable::{HitRecord, Hittable};
+use raytracer::material::{Lambertian, Material};
+use raytracer::mesh::{Face, Mesh, MeshData};
+use raytracer::obj::{self, ObjError};
+use raytracer::ray::Ray;
+use raytracer::triangle::Triangle;
+use raytracer::vec3::{self, Point3, Vec3};
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+}
+
+// Six triangles around a shared center vertex, in the z = 0 plane, with every
+// other one wound the other way.
+fn fan() -> (Vec<Triangle>, Vec<Point3>) {
+    let rim: Vec<Point3> = (0..6)
+        .map(|i| {
+            let angle = i as f64 * std::f64::consts::PI / 3.0;
+            Point3::new(angle.cos(), angle.sin(), 0.0)
+        })
+        .collect();
+    let center = Point3::new(0.0, 0.0, 0.0);
+    let triangles = (0..6)
+        .map(|i| {
+            let (a, b) = (rim[i], rim[(i + 1) % 6]);
+            if i % 2 == 0 {
+                Triangle::new(center, a, b, gray())
+            } else {
+                Triangle::new(center, b, a, gray())
+            }
+        })
+        .collect();
+    (triangles, rim)
+}
+
+fn hit_count(triangles: &[Triangle], ray: &Ray) -> usize {
+    triangles
+        .iter()
+        .filter(|t| t.hit(ray, 0.001, f64::INFINITY, &mut HitRecord::new()))
+        .count()
+}
+
+#[test]
+fn shared_edges_and_vertices_are_hit_once() {
+    let (triangles, rim) = fan();
+    let center = Point3::new(0.0, 0.0, 0.0);
+    let down = Vec3::new(0.0, 0.0, -1.0);
+
+    // Straight down onto the center and the middle of the spokes, exactly.
+    assert_eq!(hit_count(&triangles, &Ray::new(Point3::new(0.0, 0.0, 1.0), down)), 1);
+    assert_eq!(hit_count(&triangles, &Ray::new(Point3::new(0.5, 0.0, 1.0), down)), 1);
+    assert_eq!(hit_count(&triangles, &Ray::new(Point3::new(-0.5, 0.0, -1.0), -down)), 1);
+
+    // From all over, at points on the spokes and at the center, seen from
+    // either side.
+    let mut rng = StdRng::seed_from_u64(5);
+    for _ in 0..10_000 {
+        let target = if rng.random_bool(0.2) {
+            center
+        } else {
+            rim[rng.random_range(0..6)] * rng.random_range(0.05..0.95)
+        };
+        let origin = Point3::new(
+            rng.random_range(-2.0..2.0),
+            rng.random_range(-2.0..2.0),
+            rng.random_range(0.1..2.0) * if rng.random_bool(0.5) { 1.0 } else { -1.0 },
+        );
+        let ray = Ray::new(origin, target - origin);
+        assert_eq!(hit_count(&triangles, &ray), 1, "{:?} to {:?}", origin, target);
+    }
+}
+
+#[test]
+fn normals_and_uvs_are_interpolated() {
+    let data = MeshData {
+        positions: vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ],
+        normals: vec![
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(0.0, 1.0, 1.0),
+        ],
+        uvs: vec![(0.1, 0.2), (0.9, 0.2), (0.1, 0.8)],
+        faces: vec![Face {
+            positions: [0, 1, 2],
+            normals: Some([0, 1, 2]),
+            uvs: Some([0, 1, 2]),
+            material: 0,
+        }],
+        materials: vec![gray()],
+    };
+    let triangle = Triangle::in_mesh(Arc::new(data), 0);
+
+    // Weights 0.5, 0.25 and 0.25 on the three vertices.
+    let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let mut rec = HitRecord::new();
+    assert!(triangle.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+    assert!(rec.front_face);
+    let expected = vec3::unit_vector(Vec3::new(0.25, 0.25, 1.0));
+    assert!((rec.normal - expected).length() < 1e-12, "{:?}", rec.normal);
+    assert!((rec.u - 0.3).abs() < 1e-12, "{}", rec.u);
+    assert!((rec.v - 0.35).abs() < 1e-12, "{}", rec.v);
+
+    // From behind, the normal is turned toward the ray.
+    let ray = Ray::new(Point3::new(0.25, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0));
+    assert!(triangle.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+    assert!(!rec.front_face);
+    assert!((rec.normal + expected).length() < 1e-12, "{:?}", rec.normal);
+}
+
+fn parse(src: &str) -> Result<Mesh, ObjError> {
+    obj::parse_obj(src, gray(), &BTreeMap::new())
+}
+
+fn positions(mesh: &Mesh) -> Vec<[usize; 3]> {
+    mesh.data().faces.iter().map(|f| f.positions).collect()
+}
+
+const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0.5 1.5 0\n";
+
+#[test]
+fn polygons_become_fans() {
+    let mesh = parse(&format!("{}f 1 2 3 4\n", SQUARE)).unwrap();
+    assert_eq!(positions(&mesh), [[0, 1, 2], [0, 2, 3]]);
+    let mesh = parse(&format!("{}f 1 2 3 5 4\n", SQUARE)).unwrap();
+    assert_eq!(positions(&mesh), [[0, 1, 2], [0, 2, 4], [0, 4, 3]]);
+}
+
+#[test]
+fn negative_indices_count_back() {
+    let mesh = parse(&format!("{}f -5 -4 -3\nv 2 2 2\nf -1 -2 -3\n", SQUARE)).unwrap();
+    assert_eq!(positions(&mesh), [[0, 1, 2], [5, 4, 3]]);
+}
+
+#[test]
+fn corners_with_normals_or_uvs() {
+    let src = format!(
+        "{}vn 0 0 1\nvn 0 0 -1\nvt 0 0\nvt 1 0\nvt 0 1\nf 1//1 2//2 3//1\nf 1/1 2/2 3/3\nf 1/3/2 2/2/2 3/1/2\nf 1//1 2 3\n",
+        SQUARE
+    );
+    let mesh = parse(&src).unwrap();
+    let faces = &mesh.data().faces;
+    assert_eq!((faces[0].normals, faces[0].uvs), (Some([0, 1, 0]), None));
+    assert_eq!((faces[1].normals, faces[1].uvs), (None, Some([0, 1, 2])));
+    assert_eq!((faces[2].normals, faces[2].uvs), (Some([1, 1, 1]), Some([2, 1, 0])));
+    // Only used when every corner has one.
+    assert_eq!((faces[3].normals, faces[3].uvs), (None, None));
+}
+
+#[test]
+fn usemtl_picks_the_scene_material() {
+    let red: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(1.0, 0.0, 0.0)));
+    let blue: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.0, 0.0, 1.0)));
+    let default = gray();
+    let mut materials = BTreeMap::new();
+    materials.insert("red".to_string(), red.clone());
+    materials.insert("blue".to_string(), blue.clone());
+
+    let src = format!(
+        "{}f 1 2 3\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 2 3\nusemtl missing\nf 1 2 3\nusemtl red\nf 1 2 3\n",
+        SQUARE
+    );
+    let mesh = obj::parse_obj(&src, default.clone(), &materials).unwrap();
+    let data = mesh.data();
+    let used: Vec<&Arc<dyn Material>> = data.faces.iter().map(|f| &data.materials[f.material]).collect();
+    for (mat, expected) in used.iter().zip([&default, &red, &blue, &default, &red]) {
+        assert!(Arc::ptr_eq(mat, expected));
+    }
+    // Each material is stored once, however often it is used.
+    assert_eq!(data.materials.len(), 3);
+}
+
+#[test]
+fn bad_indices_are_errors() {
+    for (face, what) in [("f 1 2 6", "vertex"), ("f 1 2 -6", "vertex"), ("f 1 2 0", "vertex"), ("f 1/1 2/1 3/1", "texture")] {
+        match parse(&format!("{}{}\n", SQUARE, face)) {
+            Err(ObjError::Parse { line, message }) => {
+                assert_eq!(line, 6, "{}", face);
+                assert!(message.contains(what), "{}: {}", face, message);
+            }
+            Err(e) => panic!("{}: wrong kind of error: {}", face, e),
+            Ok(_) => panic!("{}: loaded without an error", face),
+        }
+    }
+}
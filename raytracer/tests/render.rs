@@ -0,0 +1,173 @@
+// Renders of a small lit scene, checking that every sampler gives the same image
+// bit for bit however many threads share the work, and that aiming at lights
+// gives the same brightness as only following bounces.
+
+use std::sync::Arc;
+
+use raytracer::camera::{Camera, Lens};
+use raytracer::color::Color;
+use raytracer::environment::Gradient;
+use raytracer::hittable_list::HittableList;
+use raytracer::material::{Dielectric, DiffuseLight, Lambertian, Metal};
+use raytracer::sampler::SamplerKind;
+use raytracer::scene::Scene;
+use raytracer::sphere::Sphere;
+use raytracer::vec3::{Point3, Vec3};
+use raytracer::{RenderSettings, Renderer};
+
+fn scene() -> Scene {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-0.6, 0.0, -1.0),
+        0.5,
+        Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.3)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.6, 0.0, -1.0),
+        0.5,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    let light = Arc::new(Sphere::new(
+        Point3::new(0.0, 1.5, -1.0),
+        0.3,
+        Arc::new(DiffuseLight::new(Color::new(8.0, 8.0, 8.0))),
+    ));
+    world.add(light.clone());
+    lights.add(light);
+
+    let lens = Lens {
+        aperture_radius: 0.05,
+        focus_dist: 2.0,
+        blades: 6,
+        rotation: 0.0,
+    };
+    Scene {
+        world: world.into_bvh(),
+        lights,
+        camera: Camera::with_lens(
+            Point3::new(0.0, 0.5, 1.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            60.0,
+            2.0,
+            lens,
+        ),
+        environment: Arc::new(Gradient::default()),
+        gravity: Vec::new(),
+    }
+}
+
+fn render(sampler: SamplerKind, seed: u64, threads: usize) -> Vec<u64> {
+    let settings = RenderSettings {
+        image_width: 16,
+        image_height: 8,
+        samples_per_pixel: 4,
+        max_depth: 5,
+        sampler,
+        seed,
+        threads,
+        ..Default::default()
+    };
+    let image = Renderer::new(settings).unwrap().render(&scene());
+    image
+        .pixels()
+        .iter()
+        .flat_map(|c| [c.x().to_bits(), c.y().to_bits(), c.z().to_bits()])
+        .collect()
+}
+
+#[test]
+fn renders_do_not_depend_on_threads() {
+    for sampler in [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+        SamplerKind::BlueNoise,
+    ] {
+        let single = render(sampler, 1, 1);
+        assert_eq!(single, render(sampler, 1, 1), "{:?}", sampler);
+        assert_eq!(single, render(sampler, 1, 3), "{:?}", sampler);
+        assert_ne!(single, render(sampler, 2, 1), "{:?}", sampler);
+    }
+}
+
+#[test]
+fn renderer_threads_are_reused() {
+    let settings = RenderSettings {
+        image_width: 8,
+        image_height: 4,
+        samples_per_pixel: 2,
+        max_depth: 3,
+        threads: 2,
+        ..Default::default()
+    };
+    let renderer = Renderer::new(settings).unwrap();
+    let scene = scene();
+    let first = renderer.render(&scene);
+    let second = renderer.render(&scene);
+    for (a, b) in first.pixels().iter().zip(second.pixels()) {
+        assert_eq!([a.x(), a.y(), a.z()], [b.x(), b.y(), b.z()]);
+    }
+}
+
+// A lamp over a matte floor, under a dim sky that is not sampled as a light.
+fn lamp_scene(sample_lamp: bool) -> Scene {
+    let mut world = HittableList::new();
+    let mut lights = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -100.5, -1.0),
+        100.0,
+        Arc::new(Lambertian::new(Color::new(0.7, 0.7, 0.7))),
+    )));
+    let lamp = Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, -1.0),
+        0.5,
+        Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0))),
+    ));
+    world.add(lamp.clone());
+    if sample_lamp {
+        lights.add(lamp);
+    }
+    Scene {
+        world: world.into_bvh(),
+        lights,
+        // Looking down at the lit floor, with the lamp out of view.
+        camera: Camera::look_at(
+            Point3::new(0.0, 0.3, 1.0),
+            Point3::new(0.0, -0.5, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            2.0,
+        ),
+        environment: Arc::new(Gradient::new(Color::new(0.1, 0.1, 0.1), Color::new(0.2, 0.3, 0.4))),
+        gravity: Vec::new(),
+    }
+}
+
+fn mean_value(scene: &Scene) -> f64 {
+    let settings = RenderSettings {
+        image_width: 16,
+        image_height: 8,
+        samples_per_pixel: 1024,
+        max_depth: 5,
+        sampler: SamplerKind::Sobol,
+        ..Default::default()
+    };
+    let image = Renderer::new(settings).unwrap().render(scene);
+    let sum: f64 = image.pixels().iter().map(|c| c.x() + c.y() + c.z()).sum();
+    sum / (3 * image.pixels().len()) as f64
+}
+
+#[test]
+fn light_sampling_matches_bounces_only() {
+    let mis = mean_value(&lamp_scene(true));
+    let bounces = mean_value(&lamp_scene(false));
+    assert!((mis / bounces - 1.0).abs() < 0.01, "{} with light sampling, {} without", mis, bounces);
+}
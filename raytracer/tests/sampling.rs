@@ -0,0 +1,362 @@
+// Chi-square tests of the samplers and the sample warps: samples are counted in
+// bins and compared with how many each bin should get from the distribution's
+// density.
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use raytracer::color::Color;
+use raytracer::cube::Cube;
+use raytracer::hittable::{HitRecord, Hittable};
+use raytracer::material::{DiffuseLight, Lambertian, Material, ScatterRecord};
+use raytracer::onb::{self, Onb};
+use raytracer::ray::Ray;
+use raytracer::sampler::{IndependentSampler, Sampler, SamplerKind};
+use raytracer::sphere::Sphere;
+use raytracer::transform::{self, Transform};
+use raytracer::vec3::{self, Point3, Vec3};
+
+const SAMPLES: usize = 200_000;
+const Z_BINS: usize = 10;
+const PHI_BINS: usize = 12;
+
+// Chi-square statistic of the counts against the expected fractions, failing if
+// the samples fit worse than chance allows at a 1 in 10000 level. Bins expected
+// to be (nearly) empty must stay that way.
+fn check(name: &str, observed: &[usize], expected: &[f64]) {
+    let total: usize = observed.iter().sum();
+    let mut chi2 = 0.0;
+    let mut bins = 0;
+    for (&o, &p) in observed.iter().zip(expected) {
+        let e = p * total as f64;
+        if e < 5.0 {
+            assert!(o as f64 <= 5.0 + 3.0 * e, "{}: {} samples in a bin expecting {:.2}", name, o, e);
+            continue;
+        }
+        chi2 += (o as f64 - e) * (o as f64 - e) / e;
+        bins += 1;
+    }
+
+    // Wilson-Hilferty approximation of the chi-square quantile.
+    let df = (bins - 1) as f64;
+    let z = 3.719;
+    let critical = df * (1.0 - 2.0 / (9.0 * df) + z * f64::sqrt(2.0 / (9.0 * df))).powi(3);
+    assert!(chi2 < critical, "{}: chi-square {:.1} over {:.1} with {} bins", name, chi2, critical, bins);
+}
+
+// Bin index of a value in [lo, hi) split into `n` bins.
+fn bin(x: f64, lo: f64, hi: f64, n: usize) -> usize {
+    (((x - lo) / (hi - lo) * n as f64) as usize).min(n - 1)
+}
+
+fn phi_bin(v: Vec3) -> usize {
+    let phi = f64::atan2(v.y(), v.x()).rem_euclid(2.0 * PI);
+    bin(phi, 0.0, 2.0 * PI, PHI_BINS)
+}
+
+// Checks unit directions, given in their local frame, that should be symmetric
+// around z, with `cdf(z)` the chance of a smaller z.
+fn check_directions(name: &str, directions: impl Iterator<Item = Vec3>, cdf: impl Fn(f64) -> f64) {
+    let mut observed = vec![0; Z_BINS * PHI_BINS];
+    for d in directions {
+        assert!((d.length() - 1.0).abs() < 1e-9, "{}: {} is not a unit vector", name, d);
+        observed[bin(d.z(), -1.0, 1.0, Z_BINS) * PHI_BINS + phi_bin(d)] += 1;
+    }
+    let mut expected = Vec::new();
+    for i in 0..Z_BINS {
+        let z0 = -1.0 + 2.0 * i as f64 / Z_BINS as f64;
+        let z1 = -1.0 + 2.0 * (i + 1) as f64 / Z_BINS as f64;
+        let p = cdf(z1.min(1.0)) - cdf(z0.max(-1.0));
+        expected.extend(std::iter::repeat_n(p / PHI_BINS as f64, PHI_BINS));
+    }
+    check(name, &observed, &expected);
+}
+
+fn uniforms(seed: u64) -> impl Iterator<Item = (f64, f64)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..SAMPLES).map(move |_| (rng.random(), rng.random()))
+}
+
+#[test]
+fn on_unit_sphere_is_uniform() {
+    let samples = uniforms(1).map(|(a, b)| onb::on_unit_sphere(a, b));
+    check_directions("sphere", samples, |z| (z + 1.0) / 2.0);
+    assert!((onb::on_unit_sphere_pdf() * 4.0 * PI - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn uniform_hemisphere_is_uniform() {
+    let samples = uniforms(2).map(|(a, b)| onb::uniform_hemisphere(a, b));
+    check_directions("hemisphere", samples, |z| z.max(0.0));
+    assert!((onb::uniform_hemisphere_pdf() * 2.0 * PI - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn cosine_hemisphere_follows_the_cosine() {
+    // The density z / pi integrates to z^2 over the hemisphere.
+    let samples = uniforms(3).map(|(a, b)| onb::cosine_hemisphere(a, b));
+    check_directions("cosine", samples, |z| z.max(0.0) * z.max(0.0));
+    assert!((onb::cosine_hemisphere_pdf(0.5) - 0.5 / PI).abs() < 1e-12);
+}
+
+#[test]
+fn uniform_cone_is_uniform_inside_the_cone() {
+    let cos_max = f64::cos(50f64.to_radians());
+    let samples = uniforms(4).map(|(a, b)| onb::uniform_cone(a, b, cos_max));
+    check_directions("cone", samples, |z| ((z - cos_max) / (1.0 - cos_max)).clamp(0.0, 1.0));
+    let solid_angle = 2.0 * PI * (1.0 - cos_max);
+    assert!((onb::uniform_cone_pdf(cos_max) * solid_angle - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn in_unit_disk_is_uniform() {
+    // Equal area bins: rings of equal r^2, cut into equal angles.
+    let mut observed = vec![0; Z_BINS * PHI_BINS];
+    for (a, b) in uniforms(5) {
+        let p = onb::in_unit_disk(a, b);
+        assert!(p.z() == 0.0 && p.length_squared() <= 1.0 + 1e-12);
+        observed[bin(p.length_squared(), 0.0, 1.0, Z_BINS) * PHI_BINS + phi_bin(p)] += 1;
+    }
+    let expected = vec![1.0 / (Z_BINS * PHI_BINS) as f64; Z_BINS * PHI_BINS];
+    check("disk", &observed, &expected);
+}
+
+#[test]
+fn in_unit_sphere_is_uniform() {
+    // Equal volume bins: shells of equal r^3, split by z and angle.
+    const R_BINS: usize = 5;
+    let mut rng = StdRng::seed_from_u64(6);
+    let mut observed = vec![0; R_BINS * Z_BINS * PHI_BINS];
+    for _ in 0..SAMPLES {
+        let p = onb::in_unit_sphere(rng.random(), rng.random(), rng.random());
+        let r = p.length();
+        assert!(r <= 1.0 + 1e-12);
+        let shell = bin(r * r * r, 0.0, 1.0, R_BINS);
+        let d = p / r;
+        observed[(shell * Z_BINS + bin(d.z(), -1.0, 1.0, Z_BINS)) * PHI_BINS + phi_bin(d)] += 1;
+    }
+    let expected = vec![1.0 / observed.len() as f64; observed.len()];
+    check("ball", &observed, &expected);
+}
+
+#[test]
+fn lambertian_scatters_with_the_cosine() {
+    let normal = vec3::unit_vector(Vec3::new(1.0, -2.0, 0.5));
+    let rec = HitRecord {
+        p: Point3::new(0.0, 0.0, 0.0),
+        normal,
+        t: 1.0,
+        front_face: true,
+        ..Default::default()
+    };
+    let r_in = Ray::new(Point3::new(0.0, 0.0, 0.0) + normal, -normal);
+    let lambertian = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+    let frame = Onb::new(normal);
+
+    let mut sampler = IndependentSampler::new(8);
+    let samples = (0..SAMPLES).map(|i| {
+        sampler.start_sample(0, 0, i as u32);
+        let mut srec = ScatterRecord::default();
+        assert!(lambertian.scatter(&r_in, &rec, &mut srec, &mut sampler));
+        let d = vec3::unit_vector(srec.ray.direction());
+        let local = Vec3::new(vec3::dot(d, frame.u()), vec3::dot(d, frame.v()), vec3::dot(d, frame.w()));
+
+        // The reported density is the one the directions are drawn from.
+        let pdf = srec.pdf.expect("diffuse scattering has a pdf");
+        assert!((pdf - local.z().max(0.0) / PI).abs() < 1e-9);
+        local
+    });
+    check_directions("lambertian", samples, |z| z.max(0.0) * z.max(0.0));
+}
+
+const KINDS: [SamplerKind; 5] = [
+    SamplerKind::Independent,
+    SamplerKind::Stratified,
+    SamplerKind::Halton,
+    SamplerKind::Sobol,
+    SamplerKind::BlueNoise,
+];
+
+// Numbers drawn for 16 samples in each of 32 x 32 pixels, skipping the first
+// `skip` dimensions. Dimensions past the Halton primes are checked too.
+fn sampler_values(kind: SamplerKind, skip: usize, mut draw: impl FnMut(&mut dyn Sampler)) {
+    const SPP: u32 = 16;
+    let mut sampler = kind.new_sampler(9, SPP);
+    for i in 0..32 * 32 * SPP {
+        let pixel = i / SPP;
+        sampler.start_sample(pixel % 32, pixel / 32, i % SPP);
+        for _ in 0..skip {
+            sampler.next_1d();
+        }
+        draw(sampler.as_mut());
+    }
+}
+
+#[test]
+fn samplers_are_uniform() {
+    const BINS: usize = 32;
+    for kind in KINDS {
+        for skip in [0, 7, 70] {
+            let mut observed = vec![0; BINS];
+            sampler_values(kind, skip, |sampler| {
+                let u = sampler.next_1d();
+                assert!((0.0..1.0).contains(&u));
+                observed[bin(u, 0.0, 1.0, BINS)] += 1;
+            });
+            check(&format!("{:?} dimension {}", kind, skip), &observed, &[1.0 / BINS as f64; BINS]);
+        }
+    }
+}
+
+#[test]
+fn sampler_pairs_are_uniform() {
+    const BINS: usize = 8;
+    for kind in KINDS {
+        for skip in [0, 7, 70] {
+            let mut observed = vec![0; BINS * BINS];
+            sampler_values(kind, skip, |sampler| {
+                let (u, v) = sampler.next_2d();
+                assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+                observed[bin(u, 0.0, 1.0, BINS) * BINS + bin(v, 0.0, 1.0, BINS)] += 1;
+            });
+            let name = format!("{:?} pairs after {}", kind, skip);
+            check(&name, &observed, &[1.0 / (BINS * BINS) as f64; BINS * BINS]);
+        }
+    }
+}
+
+// With 16 samples in a pixel, the well spread samplers put exactly one in each
+// sixteenth of [0, 1), and the 2D ones one in each cell of a 4 x 4 grid.
+#[test]
+fn samplers_stratify_a_pixel() {
+    for kind in [SamplerKind::Stratified, SamplerKind::Halton, SamplerKind::Sobol, SamplerKind::BlueNoise] {
+        let mut sampler = kind.new_sampler(10, 16);
+        let mut strata = [0; 16];
+        for i in 0..16 {
+            sampler.start_sample(3, 4, i);
+            strata[bin(sampler.next_1d(), 0.0, 1.0, 16)] += 1;
+        }
+        assert_eq!(strata, [1; 16], "{:?}", kind);
+    }
+
+    for kind in [SamplerKind::Stratified, SamplerKind::Sobol, SamplerKind::BlueNoise] {
+        let mut sampler = kind.new_sampler(10, 16);
+        for skip in [0, 3, 40] {
+            let mut cells = [0; 16];
+            for i in 0..16 {
+                sampler.start_sample(3, 4, i);
+                for _ in 0..skip {
+                    sampler.next_1d();
+                }
+                let (u, v) = sampler.next_2d();
+                cells[bin(u, 0.0, 1.0, 4) * 4 + bin(v, 0.0, 1.0, 4)] += 1;
+            }
+            assert_eq!(cells, [1; 16], "{:?} after {}", kind, skip);
+        }
+    }
+}
+
+#[test]
+fn samplers_repeat_exactly() {
+    for kind in KINDS {
+        // The numbers of a sample don't depend on what was drawn before it.
+        let draw = |sampler: &mut dyn Sampler, x, y, index| {
+            sampler.start_sample(x, y, index);
+            (0..20).map(|_| sampler.next_1d().to_bits()).collect::<Vec<_>>()
+        };
+        let mut a = kind.new_sampler(11, 8);
+        let mut b = kind.new_sampler(11, 8);
+        let first = draw(a.as_mut(), 5, 6, 7);
+        draw(b.as_mut(), 1, 2, 3);
+        assert_eq!(first, draw(b.as_mut(), 5, 6, 7), "{:?}", kind);
+
+        // While other pixels, samples and seeds get other numbers.
+        assert_ne!(first, draw(b.as_mut(), 6, 5, 7), "{:?}", kind);
+        assert_ne!(first, draw(b.as_mut(), 5, 6, 2), "{:?}", kind);
+        let mut c = kind.new_sampler(12, 8);
+        assert_ne!(first, draw(c.as_mut(), 5, 6, 7), "{:?}", kind);
+    }
+}
+
+// The blue noise sampler spreads samples over neighbourhoods too: with 4 samples
+// each, the 64 samples of an aligned 4 x 4 block of pixels fill an 8 x 8 grid.
+#[test]
+fn blue_noise_stratifies_neighbouring_pixels() {
+    let mut sampler = SamplerKind::BlueNoise.new_sampler(13, 4);
+    for (bx, by, skip) in [(0, 0, 0), (4, 8, 0), (12, 4, 3), (8, 8, 7)] {
+        let mut cells = [0; 64];
+        for y in by..by + 4 {
+            for x in bx..bx + 4 {
+                for i in 0..4 {
+                    sampler.start_sample(x, y, i);
+                    for _ in 0..skip {
+                        sampler.next_1d();
+                    }
+                    let (u, v) = sampler.next_2d();
+                    cells[bin(u, 0.0, 1.0, 8) * 8 + bin(v, 0.0, 1.0, 8)] += 1;
+                }
+            }
+        }
+        assert_eq!(cells, [1; 64], "block at ({}, {}) after {}", bx, by, skip);
+    }
+}
+
+// Lights moved, turned and stretched by a transform: the density integrates to 1
+// over all directions, and the directions `random` picks follow it, which shows up
+// as 1 / pdf averaging to the solid angle the light covers.
+#[test]
+fn transformed_lights_sample_their_density() {
+    let light = Arc::new(DiffuseLight::new(Color::new(1.0, 1.0, 1.0)));
+    let matrix = transform::translation(Vec3::new(0.5, 2.0, -1.0))
+        * transform::rotation(Vec3::new(1.0, 1.0, 0.0), 30.0)
+        * transform::scaling(Vec3::new(2.0, 0.5, 1.0));
+    let objects: [(&str, Arc<dyn Hittable>); 2] = [
+        ("sphere", Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.5, light.clone()))),
+        (
+            "cube",
+            Arc::new(Cube::new(Point3::new(-0.5, -0.5, -0.5), Point3::new(0.5, 0.5, 0.5), light)),
+        ),
+    ];
+    let origin = Point3::new(0.0, 0.0, 0.0);
+
+    for (name, object) in objects {
+        let moved = Transform::new(object, matrix);
+        let mut rng = StdRng::seed_from_u64(14);
+
+        // Uniform directions, to integrate the density and to measure the solid
+        // angle by counting hits.
+        let (mut integral, mut hits) = (0.0, 0);
+        for _ in 0..SAMPLES {
+            let d = onb::on_unit_sphere(rng.random(), rng.random());
+            integral += moved.pdf_value(origin, d) / onb::on_unit_sphere_pdf();
+            let mut rec = HitRecord::new();
+            if moved.hit(&Ray::new(origin, d), 0.001, f64::INFINITY, &mut rec) {
+                hits += 1;
+            }
+        }
+        let integral = integral / SAMPLES as f64;
+        let solid_angle = 4.0 * PI * hits as f64 / SAMPLES as f64;
+        assert!((integral - 1.0).abs() < 0.03, "{}: density integrates to {}", name, integral);
+
+        let mut sampler = IndependentSampler::new(15);
+        let mut inverse_pdf = 0.0;
+        for i in 0..SAMPLES {
+            sampler.start_sample(0, 0, i as u32);
+            let d = moved.random(origin, &mut sampler);
+            let pdf = moved.pdf_value(origin, d);
+            assert!(pdf > 0.0, "{}: picked {} where the density is 0", name, d);
+            inverse_pdf += 1.0 / pdf;
+        }
+        let estimate = inverse_pdf / SAMPLES as f64;
+        assert!(
+            (estimate / solid_angle - 1.0).abs() < 0.03,
+            "{}: solid angle {} from samples, {} from hits",
+            name,
+            estimate,
+            solid_angle
+        );
+    }
+}
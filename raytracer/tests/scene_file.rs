@@ -0,0 +1,114 @@
+// Loading scene files: a small valid scene, and the errors that point at the
+// line and key of a bad one.
+
+use std::path::Path;
+
+use raytracer::hittable::{HitRecord, Hittable};
+use raytracer::ray::Ray;
+use raytracer::sampler::IndependentSampler;
+use raytracer::scene::Scene;
+use raytracer::scene_file::{self, SceneError};
+use raytracer::vec3::{Point3, Vec3};
+
+const SCENE: &str = r#"
+[camera]
+look_from = [0.0, 0.0, 3.0]
+look_at = [0.0, 0.0, 0.0]
+vfov = 40.0
+
+[materials.gray]
+type = "lambertian"
+albedo = [0.5, 0.5, 0.5]
+
+[materials.lamp]
+type = "diffuse_light"
+emit = [4.0, 4.0, 4.0]
+
+[[objects]]
+type = "sphere"
+center = [-2.0, 0.0, 0.0]
+radius = 0.5
+material = "gray"
+
+[[objects]]
+type = "sphere"
+center = [0.0, 0.0, 0.0]
+radius = 0.5
+material = "lamp"
+
+[[objects]]
+type = "cube"
+min = [1.5, -0.5, -0.5]
+max = [2.5, 0.5, 0.5]
+material = "lamp"
+"#;
+
+fn parse(src: &str) -> Result<Scene, SceneError> {
+    scene_file::parse_scene(src, Path::new("."), 2.0)
+}
+
+// Distance along a ray down from y = 10 above `x`, if it hits anything.
+fn hit_from_above(scene: &Scene, x: f64) -> Option<f64> {
+    let ray = Ray::new(Point3::new(x, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    let mut rec = HitRecord::new();
+    scene.world.hit(&ray, 0.001, f64::INFINITY, &mut rec).then_some(rec.t)
+}
+
+#[test]
+fn parses_a_small_scene() {
+    let scene = parse(SCENE).unwrap();
+
+    // One object at each of x = -2, 0 and 2, with nothing between them.
+    for x in [-2.0, 0.0, 2.0] {
+        let t = hit_from_above(&scene, x).unwrap_or_else(|| panic!("nothing at x = {}", x));
+        assert!((t - 9.5).abs() < 1e-9, "x = {}: t = {}", x, t);
+    }
+    for x in [-1.0, 1.0, 3.0] {
+        assert_eq!(hit_from_above(&scene, x), None, "x = {}", x);
+    }
+    // Only the two glowing objects are sampled as lights.
+    assert_eq!(scene.lights.objects().len(), 2);
+    assert!(scene.gravity.is_empty());
+
+    // No aperture, so the center ray leaves look_from straight at look_at.
+    let ray = scene.camera.get_ray(0.5, 0.5, &mut IndependentSampler::new(1));
+    assert!((ray.origin() - Point3::new(0.0, 0.0, 3.0)).length() < 1e-12);
+    let dir = ray.direction() / ray.direction().length();
+    assert!((dir - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12, "{:?}", dir);
+}
+
+fn invalid(src: &str) -> (usize, String) {
+    match parse(src) {
+        Err(SceneError::Invalid { line, key, .. }) => (line, key),
+        Err(e) => panic!("wrong kind of error: {}", e),
+        Ok(_) => panic!("bad scene loaded without an error"),
+    }
+}
+
+#[test]
+fn unknown_material_is_an_error() {
+    let src = SCENE.replace("material = \"gray\"", "material = \"grey\"");
+    assert_eq!(invalid(&src), (15, "objects[0].material".to_string()));
+}
+
+#[test]
+fn bad_radius_is_an_error() {
+    let src = SCENE.replace("radius = 0.5\nmaterial = \"lamp\"", "radius = -0.5\nmaterial = \"lamp\"");
+    assert_eq!(invalid(&src), (21, "objects[1]".to_string()));
+    // NaN is no better than a negative radius.
+    let src = SCENE.replace("radius = 0.5\nmaterial = \"lamp\"", "radius = nan\nmaterial = \"lamp\"");
+    assert_eq!(invalid(&src), (21, "objects[1]".to_string()));
+}
+
+#[test]
+fn unknown_key_is_an_error() {
+    let src = SCENE.replace("vfov = 40.0", "vfov = 40.0\nfov = 40.0");
+    let e = match parse(&src) {
+        Err(SceneError::Parse(e)) => e,
+        Err(e) => panic!("wrong kind of error: {}", e),
+        Ok(_) => panic!("bad scene loaded without an error"),
+    };
+    let line = src[..e.span().unwrap().start].matches('\n').count() + 1;
+    assert_eq!(line, 6);
+    assert!(e.message().contains("unknown field `fov`"), "{}", e);
+}
@@ -0,0 +1,76 @@
+// Light bending around a Schwarzschild black hole, checked against the known
+// results for the deflection angle and the photon sphere.
+
+use std::f64::consts::PI;
+
+use raytracer::gravity::{GravitySource, G};
+use raytracer::integrator::{integrate_ray_path, Marching};
+use raytracer::ray::Ray;
+use raytracer::vec3::{self, Point3, Vec3};
+
+// Mass with a Schwarzschild radius of 1.
+const MASS: f64 = 0.5 / G;
+
+// Shoot a ray along +x from far away, passing the origin at distance `b`, and
+// return how far it turned, or None when it was captured.
+fn deflection(source: GravitySource, b: f64, distance: f64, tolerance: f64) -> Option<f64> {
+    let r = Ray::new(Point3::new(-distance, b, 0.0), Vec3::new(1.0, 0.0, 0.0));
+    let march = Marching {
+        max_time: 2.0 * distance,
+        max_step: distance / 10.0,
+        tolerance,
+    };
+    let out = integrate_ray_path(&r, &[source], &march)?;
+    let dir = out.direction();
+    Some(f64::atan2(
+        vec3::cross(r.direction(), dir).length(),
+        vec3::dot(r.direction(), dir),
+    ))
+}
+
+// Weak field expansion of the exact deflection in terms of r_s / b.
+fn expected_deflection(b: f64) -> f64 {
+    let x = 1.0 / b;
+    2.0 * x + 15.0 * PI / 16.0 * x * x + 16.0 / 3.0 * x * x * x
+}
+
+#[test]
+fn deflection_matches_weak_field_expansion() {
+    let hole = GravitySource::black_hole(Point3::new(0.0, 0.0, 0.0), MASS);
+    for b in [50.0, 100.0, 200.0] {
+        let angle = deflection(hole, b, 1e4, 1e-9).unwrap();
+        let expected = expected_deflection(b);
+        assert!(
+            (angle - expected).abs() < 1e-5,
+            "b = {}: deflection {} expected {}",
+            b,
+            angle,
+            expected
+        );
+    }
+}
+
+#[test]
+fn deflection_is_twice_newtonian() {
+    let hole = GravitySource::black_hole(Point3::new(0.0, 0.0, 0.0), MASS);
+    let newtonian = GravitySource::new(Point3::new(0.0, 0.0, 0.0), MASS);
+    let b = 1000.0;
+    let relativistic = deflection(hole, b, 1e5, 1e-8).unwrap();
+    let classic = deflection(newtonian, b, 1e5, 1e-8).unwrap();
+    assert!((relativistic / classic - 2.0).abs() < 1e-2);
+}
+
+#[test]
+fn photon_sphere_separates_captured_and_escaping_rays() {
+    let hole = GravitySource::black_hole(Point3::new(0.0, 0.0, 0.0), MASS);
+    // Critical impact parameter 3 * sqrt(3) / 2 * r_s.
+    let critical = 1.5 * f64::sqrt(3.0);
+    assert!(deflection(hole, critical * 0.99, 50.0, 1e-8).is_none());
+    assert!(deflection(hole, critical * 1.01, 50.0, 1e-8).is_some());
+}
+
+#[test]
+fn head_on_ray_is_captured() {
+    let hole = GravitySource::black_hole(Point3::new(0.0, 0.0, 0.0), MASS);
+    assert!(deflection(hole, 0.0, 50.0, 1e-6).is_none());
+}
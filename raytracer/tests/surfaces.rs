@@ -0,0 +1,124 @@
+// Hits on cubes from inside and on flat boxes, and which way glass sends a ray.
+
+use std::sync::Arc;
+
+use raytracer::color::Color;
+use raytracer::cube::Cube;
+use raytracer::hittable::{HitRecord, Hittable};
+use raytracer::material::{Dielectric, Lambertian, Material, ScatterRecord};
+use raytracer::ray::Ray;
+use raytracer::sampler::Sampler;
+use raytracer::vec3::{self, Point3, Vec3};
+
+fn gray() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+}
+
+#[test]
+fn cube_hit_from_inside() {
+    let cube = Cube::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0), gray());
+    for dir in [
+        Vec3::new(1.0, 0.2, 0.1),
+        Vec3::new(-0.3, 1.0, 0.2),
+        Vec3::new(0.1, -0.2, -1.0),
+    ] {
+        let ray = Ray::new(Point3::new(0.1, 0.2, 0.3), dir);
+        let mut rec = HitRecord::new();
+        assert!(cube.hit(&ray, 0.001, f64::INFINITY, &mut rec), "{:?}", dir);
+        assert!(!rec.front_face, "{:?}", dir);
+        // Leaving through the face the direction points at, with the normal
+        // turned back against the ray.
+        assert!(vec3::dot(rec.normal, dir) < 0.0, "{:?}", dir);
+        assert!((rec.normal.length() - 1.0).abs() < 1e-12);
+        let p = rec.p;
+        assert!([p.x(), p.y(), p.z()].iter().any(|c| (c.abs() - 1.0).abs() < 1e-12), "{:?}", p);
+    }
+}
+
+#[test]
+fn flat_cube_has_valid_uvs() {
+    let cube = Cube::new(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 0.0, 1.0), gray());
+    let ray = Ray::new(Point3::new(1.0, 5.0, 0.25), Vec3::new(0.0, -1.0, 0.0));
+    let mut rec = HitRecord::new();
+    assert!(cube.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+    assert!((rec.u - 0.5).abs() < 1e-12 && (rec.v - 0.75).abs() < 1e-12, "{} {}", rec.u, rec.v);
+
+    // Hit edge on, the missing coordinate is 0 instead of NaN.
+    let cube = Cube::new(Point3::new(0.0, 0.0, 0.0), Point3::new(2.0, 1.0, 0.0), gray());
+    let ray = Ray::new(Point3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+    let mut rec = HitRecord::new();
+    assert!(cube.hit(&ray, 0.001, f64::INFINITY, &mut rec));
+    assert_eq!((rec.u, rec.v), (0.0, 0.5));
+}
+
+// Always returns the same number, to pick one side of the reflect or refract
+// choice.
+struct Fixed(f64);
+
+impl Sampler for Fixed {
+    fn start_sample(&mut self, _x: u32, _y: u32, _index: u32) {}
+
+    fn next_1d(&mut self) -> f64 {
+        self.0
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.0, self.0)
+    }
+}
+
+// Shoot a ray at the glass surface z = 0 (normal +z) at `degrees` from the
+// normal, from outside (above) or inside (below), and say whether it reflected.
+fn reflects(degrees: f64, outside: bool, u: f64) -> bool {
+    let glass = Dielectric::new(1.5);
+    let theta = degrees.to_radians();
+    let z = if outside { -1.0 } else { 1.0 };
+    let dir = Vec3::new(theta.sin(), 0.0, z * theta.cos());
+    let r_in = Ray::new(Point3::new(0.0, 0.0, 0.0) - dir, dir);
+    let mut rec = HitRecord::new();
+    rec.p = Point3::new(0.0, 0.0, 0.0);
+    rec.set_face_normal(&r_in, Vec3::new(0.0, 0.0, 1.0));
+    assert_eq!(rec.front_face, outside);
+
+    let mut srec = ScatterRecord::default();
+    assert!(glass.scatter(&r_in, &rec, &mut srec, &mut Fixed(u)));
+    assert!(srec.pdf.is_none());
+    let out = srec.ray.direction();
+    assert!((out.length() - 1.0).abs() < 1e-9);
+    // Reflected rays stay on the side they came from.
+    let reflected = out.z() * z < 0.0;
+    if reflected {
+        let mirror = Vec3::new(dir.x(), dir.y(), -dir.z());
+        assert!((out - mirror).length() < 1e-9, "{:?}", out);
+    }
+    reflected
+}
+
+#[test]
+fn glass_reflects_inside_past_the_critical_angle() {
+    // asin(1 / 1.5) is about 41.8 degrees. Past it nothing gets out, whatever
+    // the Fresnel term would say.
+    for degrees in [30.0, 41.0] {
+        assert!(!reflects(degrees, false, 0.999), "{}", degrees);
+    }
+    for degrees in [42.0, 60.0, 85.0, 89.9] {
+        assert!(reflects(degrees, false, 0.999), "{}", degrees);
+        assert!(reflects(degrees, false, 0.0), "{}", degrees);
+    }
+    // From outside there is always a way in.
+    for degrees in [42.0, 60.0, 85.0] {
+        assert!(!reflects(degrees, true, 0.999), "{}", degrees);
+    }
+}
+
+#[test]
+fn glass_schlick_endpoints() {
+    // Head on, Schlick gives ((1 - 1.5) / (1 + 1.5))^2 = 0.04 of the light back.
+    assert!(reflects(0.0, true, 0.039));
+    assert!(!reflects(0.0, true, 0.041));
+    assert!(reflects(0.0, false, 0.039));
+    assert!(!reflects(0.0, false, 0.041));
+    // At a grazing angle nearly all of it: 0.04 + 0.96 (1 - cos 89.99)^5 = 0.99916.
+    assert!(reflects(89.99, true, 0.999));
+    assert!(!reflects(89.99, true, 0.9995));
+}
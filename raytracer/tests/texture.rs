@@ -0,0 +1,132 @@
+// Textures: the 3D checker, image lookups at and past the edges, and the
+// ranges of the noise.
+
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use raytracer::color::Color;
+use raytracer::image::{ImageBuffer, Wrap};
+use raytracer::perlin::Perlin;
+use raytracer::texture::{Checker, ImageTexture, NoisePattern, NoiseTexture, SolidColor, Texture};
+use raytracer::vec3::Point3;
+
+fn same(a: Color, b: Color) -> bool {
+    (a - b).length() < 1e-9
+}
+
+#[test]
+fn checker_alternates_across_cells() {
+    let white = Color::new(1.0, 1.0, 1.0);
+    let black = Color::new(0.0, 0.0, 0.0);
+    let checker = Checker::new(0.5, Arc::new(SolidColor::new(white)), Arc::new(SolidColor::new(black)));
+    let at = |x: f64, y: f64, z: f64| checker.value(0.0, 0.0, Point3::new(x, y, z));
+
+    // Both sides of zero, so the cells below it count the right way too.
+    for base in [Point3::new(0.1, 0.2, 0.3), Point3::new(-1.9, -0.8, -3.3), Point3::new(7.2, -2.4, 0.05)] {
+        let here = at(base.x(), base.y(), base.z());
+        // Anywhere else in the same half unit cell is the same color.
+        let cell = base / 0.5;
+        let corner = 0.5 * Point3::new(cell.x().floor(), cell.y().floor(), cell.z().floor());
+        for d in [0.01, 0.25, 0.49] {
+            assert!(same(at(corner.x() + d, corner.y() + 0.49 - d, corner.z() + d), here));
+        }
+        // One step over along any axis is the other color, two steps the same.
+        for step in [Point3::new(0.5, 0.0, 0.0), Point3::new(0.0, 0.5, 0.0), Point3::new(0.0, 0.0, 0.5)] {
+            let one = base + step;
+            let two = base + 2.0 * step;
+            assert!(!same(at(one.x(), one.y(), one.z()), here), "{:?} + {:?}", base, step);
+            assert!(same(at(two.x(), two.y(), two.z()), here), "{:?} + {:?}", base, step);
+        }
+    }
+    assert!(same(at(0.25, 0.25, 0.25), white));
+    assert!(same(at(-0.25, 0.25, 0.25), black));
+}
+
+// 4x2 image with x in red and y in green.
+fn ramp() -> ImageBuffer {
+    let pixels = (0..8).map(|i| Color::new((i % 4) as f64, (i / 4) as f64, 0.0)).collect();
+    ImageBuffer::from_pixels(4, 2, pixels)
+}
+
+#[test]
+fn image_texture_orientation_and_wrapping() {
+    let clamp = ImageTexture::new(ramp(), Wrap::Clamp, Wrap::Clamp);
+    let repeat = ImageTexture::new(ramp(), Wrap::Repeat, Wrap::Repeat);
+    let mixed = ImageTexture::new(ramp(), Wrap::Repeat, Wrap::Clamp);
+    let origin = Point3::new(0.0, 0.0, 0.0);
+
+    // Pixel centers come back exactly, with v = 0 at the bottom row.
+    for (texture, name) in [(&clamp, "clamp"), (&repeat, "repeat")] {
+        for x in 0..4 {
+            for y in 0..2 {
+                let (u, v) = ((x as f64 + 0.5) / 4.0, 1.0 - (y as f64 + 0.5) / 2.0);
+                let c = texture.value(u, v, origin);
+                assert!(same(c, Color::new(x as f64, y as f64, 0.0)), "{} ({}, {}): {:?}", name, x, y, c);
+            }
+        }
+    }
+
+    // Past the edges, clamping holds the edge pixels.
+    assert!(same(clamp.value(-0.3, 0.75, origin), Color::new(0.0, 0.0, 0.0)));
+    assert!(same(clamp.value(1.7, 0.75, origin), Color::new(3.0, 0.0, 0.0)));
+    assert!(same(clamp.value(0.125, -2.0, origin), Color::new(0.0, 1.0, 0.0)));
+    assert!(same(clamp.value(0.125, 5.0, origin), Color::new(0.0, 0.0, 0.0)));
+    // At the very edge it doesn't blend in the far column.
+    assert!(same(clamp.value(0.0, 0.75, origin), Color::new(0.0, 0.0, 0.0)));
+
+    // Repeating tiles the image, and blends across the seam.
+    for (u, v) in [(0.3, 0.6), (0.05, 0.9), (0.8, 0.2)] {
+        for (du, dv) in [(1.0, 0.0), (-1.0, 0.0), (0.0, 2.0), (3.0, -1.0)] {
+            assert!(same(repeat.value(u + du, v + dv, origin), repeat.value(u, v, origin)));
+        }
+    }
+    assert!(same(repeat.value(0.0, 0.75, origin), Color::new(1.5, 0.0, 0.0)));
+    assert!(same(repeat.value(0.125, 1.0, origin), Color::new(0.0, 0.5, 0.0)));
+
+    // Each direction follows its own setting.
+    assert!(same(mixed.value(1.125, 0.75, origin), Color::new(0.0, 0.0, 0.0)));
+    assert!(same(mixed.value(0.125, 1.5, origin), Color::new(0.0, 0.0, 0.0)));
+}
+
+#[test]
+fn noise_stays_in_range() {
+    let mut rng = StdRng::seed_from_u64(5);
+    let perlin = Perlin::new(11);
+    let white = Color::new(1.0, 1.0, 1.0);
+    let textures = [NoisePattern::Perlin, NoisePattern::Turbulence, NoisePattern::Marble]
+        .map(|pattern| (pattern, NoiseTexture::new(pattern, 4.0, white, 11)));
+
+    let (mut lowest, mut highest) = (0.0f64, 0.0f64);
+    for _ in 0..20_000 {
+        let p = Point3::new(
+            rng.random_range(-300.0..300.0),
+            rng.random_range(-300.0..300.0),
+            rng.random_range(-300.0..300.0),
+        );
+        // With unit gradients Perlin noise can't get past sqrt(3) / 2.
+        let n = perlin.noise(p);
+        assert!(n.abs() <= 0.75f64.sqrt(), "{} at {:?}", n, p);
+        lowest = lowest.min(n);
+        highest = highest.max(n);
+
+        // The octaves add up to less than twice the first one.
+        let t = perlin.turbulence(p, 7);
+        assert!((0.0..2.0 * 0.75f64.sqrt()).contains(&t), "{} at {:?}", t, p);
+
+        for (pattern, texture) in &textures {
+            let c = texture.value(0.0, 0.0, p);
+            let top = if *pattern == NoisePattern::Turbulence { 2.0 } else { 1.0 };
+            assert!(c.x() >= 0.0 && c.x() <= top, "{:?} {} at {:?}", pattern, c.x(), p);
+            assert!(c.x() == c.y() && c.y() == c.z());
+        }
+    }
+    // It does use a good part of that range, both ways.
+    assert!(lowest < -0.4 && highest > 0.4, "{} {}", lowest, highest);
+
+    // Zero on the lattice, and the same for the same seed.
+    assert_eq!(perlin.noise(Point3::new(3.0, -7.0, 12.0)), 0.0);
+    let p = Point3::new(1.3, 2.7, -0.4);
+    assert_eq!(Perlin::new(11).noise(p), perlin.noise(p));
+}
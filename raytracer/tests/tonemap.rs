@@ -0,0 +1,68 @@
+// The display pipeline: the sRGB curve, the tone mapping operators, and
+// dithering that keeps the average brightness of what it quantizes.
+
+use raytracer::color::Color;
+use raytracer::image::ImageBuffer;
+use raytracer::tonemap::{self, Dither, DisplayTransform, ToneMap};
+
+#[test]
+fn srgb_endpoints_and_round_trip() {
+    assert_eq!(tonemap::srgb_encode(0.0), 0.0);
+    assert_eq!(tonemap::srgb_decode(0.0), 0.0);
+    assert!((tonemap::srgb_encode(1.0) - 1.0).abs() < 1e-12);
+    assert!((tonemap::srgb_decode(1.0) - 1.0).abs() < 1e-12);
+
+    // Linear up to 0.0031308, and the two pieces meet there.
+    assert_eq!(tonemap::srgb_encode(0.002), 12.92 * 0.002);
+    assert_eq!(tonemap::srgb_decode(0.02), 0.02 / 12.92);
+    let below = tonemap::srgb_encode(0.0031308);
+    let above = tonemap::srgb_encode(0.0031308 + 1e-12);
+    assert!((below - above).abs() < 1e-6, "{} {}", below, above);
+    assert!((below - 0.04045).abs() < 1e-6, "{}", below);
+
+    for i in 0..=1000 {
+        let v = i as f64 / 1000.0;
+        let back = tonemap::srgb_decode(tonemap::srgb_encode(v));
+        assert!((back - v).abs() < 1e-6, "{} came back as {}", v, back);
+        let back = tonemap::srgb_encode(tonemap::srgb_decode(v));
+        assert!((back - v).abs() < 1e-6, "{} came back as {}", v, back);
+    }
+}
+
+#[test]
+fn tone_maps_are_monotonic_from_zero() {
+    for tone_map in [ToneMap::Reinhard, ToneMap::Hable, ToneMap::Aces] {
+        assert!(tone_map.apply(0.0).abs() < 1e-12, "{:?}", tone_map);
+        let mut previous = tone_map.apply(0.0);
+        for i in 1..=20_000 {
+            let x = i as f64 * 0.005;
+            let y = tone_map.apply(x);
+            assert!(y > previous, "{:?} is not increasing at {}", tone_map, x);
+            previous = y;
+        }
+    }
+}
+
+// Average 8 bit value of a flat image whose exact value would be `level`.
+fn mean_level(level: f64, dither: Dither) -> f64 {
+    let gray = tonemap::srgb_decode(level / 255.0);
+    let image = ImageBuffer::from_pixels(64, 64, vec![Color::new(gray, gray, gray); 64 * 64]);
+    let display = DisplayTransform {
+        dither,
+        ..Default::default()
+    };
+    let ldr = display.to_ldr(&image, 255);
+    ldr.data.iter().map(|&v| v as f64).sum::<f64>() / ldr.data.len() as f64
+}
+
+#[test]
+fn dithering_keeps_the_mean() {
+    for level in [0.3, 100.25, 180.75, 254.9] {
+        // Without dithering every pixel rounds the same way.
+        assert_eq!(mean_level(level, Dither::None), level.round());
+        for dither in [Dither::Ordered, Dither::BlueNoise] {
+            let mean = mean_level(level, dither);
+            assert!((mean - level).abs() < 1.0 / 64.0, "{:?} at {}: {}", dither, level, mean);
+        }
+    }
+}